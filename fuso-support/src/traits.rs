@@ -13,10 +13,10 @@
 // limitations under the License.
 
 use codec::Codec;
-use frame_support::{traits::BalanceStatus, Parameter};
+use frame_support::{traits::BalanceStatus, weights::Weight, Parameter};
 use sp_runtime::{
     traits::{AtLeast32BitUnsigned, MaybeSerializeDeserialize, Member},
-    DispatchError, DispatchResult,
+    DispatchError, DispatchResult, Perquintill,
 };
 
 pub trait Token<AccountId> {
@@ -175,5 +175,43 @@ pub trait Rewarding<AccountId, Volume: Copy, BlockNumber> {
 
     fn acked_reward(who: &AccountId) -> Self::Balance;
 
+    /// whether the window of `era` has fully elapsed at the block `at`
+    fn is_era_closed(era: BlockNumber, at: BlockNumber) -> bool;
+
+    /// the fraction of a closed era's volume contributed by `who`
+    fn era_share(who: &AccountId, era: BlockNumber) -> Perquintill;
+
     fn save_trading(trader: &AccountId, amount: Volume, at: BlockNumber) -> DispatchResult;
+
+    /// record the trading of a pair quoting or based on the reward token itself
+    fn save_native_trading(trader: &AccountId, amount: Volume, at: BlockNumber) -> DispatchResult;
+
+    /// the weight of `save_trading`, which is higher if it settles a pending era
+    fn save_trading_weight(trader: &AccountId, at: BlockNumber) -> Weight;
+
+    /// record a fill of `maker` against `taker`, which may be the same account
+    fn save_trading_pair(
+        maker: &AccountId,
+        taker: &AccountId,
+        amount: Volume,
+        at: BlockNumber,
+    ) -> DispatchResult;
+
+    /// whether the volume of `who` would accrue rewards, for the matcher to check cheaply
+    /// before routing fills to it
+    fn is_eligible(who: &AccountId) -> bool;
+
+    /// assert the total volume of `era` matches the one computed independently by the matcher
+    fn confirm_volume_checkpoint(era: BlockNumber, expected_total: Volume) -> DispatchResult;
+
+    /// revoke the volume recorded for `trader` in the open era containing `at`
+    fn revoke_trading(trader: &AccountId, amount: Volume, at: BlockNumber) -> DispatchResult;
+
+    /// record holding `position` for `duration` blocks as the volume `position * duration`
+    fn save_holding(
+        trader: &AccountId,
+        position: Volume,
+        duration: BlockNumber,
+        at: BlockNumber,
+    ) -> DispatchResult;
 }
@@ -0,0 +1,69 @@
+// Copyright 2021 UINB Technologies Pte. Ltd.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_runtime::{traits::MaybeDisplay, DispatchError, Perquintill};
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+    /// API to interact with pallet-fuso-reward
+    pub trait FusoRewardRuntimeApi<AccountId, Balance, Volume, BlockNumber>
+    where
+        AccountId: Codec + MaybeDisplay,
+        Balance: Codec + MaybeDisplay,
+        Volume: Codec + MaybeDisplay,
+        BlockNumber: Codec + MaybeDisplay,
+    {
+        fn total_claimable(who: AccountId) -> Balance;
+
+        fn simulate_era(era: BlockNumber, start: u32, count: u32) -> Vec<(AccountId, Balance)>;
+
+        fn claim_rate() -> Perquintill;
+
+        fn next_era_emission(at: BlockNumber) -> Balance;
+
+        fn pending_rewards_batch(accounts: Vec<AccountId>) -> Vec<Balance>;
+
+        fn volume_history(
+            from_era: BlockNumber,
+            to_era: BlockNumber,
+        ) -> Result<Vec<(BlockNumber, Volume)>, DispatchError>;
+
+        fn era_rank(who: AccountId, era: BlockNumber) -> (u32, u32);
+
+        fn reward_delta_since(who: AccountId, since_era: BlockNumber) -> Balance;
+
+        fn max_possible_reward(who: AccountId, era: BlockNumber) -> Balance;
+
+        fn reward_breakdown(who: AccountId) -> (Balance, Balance, Volume, BlockNumber);
+
+        fn verify_era(era: BlockNumber) -> bool;
+
+        fn export_state(
+            cursor: Option<Vec<u8>>,
+        ) -> (Vec<(AccountId, Balance, Volume, BlockNumber)>, Option<Vec<u8>>);
+
+        fn export_volumes(
+            cursor: Option<Vec<u8>>,
+        ) -> (Vec<(BlockNumber, AccountId, Volume)>, Option<Vec<u8>>);
+
+        fn pool_account() -> AccountId;
+
+        fn pool_balance() -> Balance;
+
+        fn marginal_reward_per_volume(era: BlockNumber, at: BlockNumber) -> Balance;
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright 2021 UINB Technologies Pte. Ltd.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite};
+use frame_support::traits::Get;
+use fuso_support::traits::Rewarding;
+use sp_runtime::traits::Convert;
+
+benchmarks! {
+    where_clause {
+        where
+            Balance<T>: From<u128>,
+    }
+
+    save_trading_same_era {
+        let trader: T::AccountId = account("trader", 0, 0);
+        let era_duration = T::EraDuration::get();
+        let vol = T::U128ToVolume::convert(1000);
+        frame_system::Pallet::<T>::set_block_number(era_duration);
+        Pallet::<T>::save_trading(&trader, vol, era_duration)?;
+        // the first trade of the era may be boosted, the second isn't
+        let pending = Pallet::<T>::rewards(&trader).pending_vol;
+    }: {
+        Pallet::<T>::save_trading(&trader, vol, era_duration)?;
+    }
+    verify {
+        assert_eq!(Pallet::<T>::rewards(&trader).pending_vol, pending + vol);
+    }
+
+    save_trading_rotate {
+        let trader: T::AccountId = account("trader", 0, 0);
+        let era_duration = T::EraDuration::get();
+        let next_era = era_duration + era_duration;
+        let vol = T::U128ToVolume::convert(1000);
+        frame_system::Pallet::<T>::set_block_number(next_era);
+        Pallet::<T>::save_trading(&trader, vol, era_duration)?;
+    }: {
+        Pallet::<T>::save_trading(&trader, vol, next_era)?;
+    }
+    verify {
+        // whatever the pool of the settled era is, the trader holds exactly what it confirmed
+        let reward = Pallet::<T>::rewards(&trader);
+        assert_eq!(reward.last_modify, next_era);
+        assert_eq!(reward.confirmed, Pallet::<T>::era_rewards(era_duration, &trader));
+    }
+}
+
+impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
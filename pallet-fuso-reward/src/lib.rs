@@ -16,34 +16,57 @@
 
 pub use pallet::*;
 
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
 #[cfg(test)]
 pub mod mock;
+#[cfg(test)]
+pub mod tests;
+pub mod weights;
 
 #[frame_support::pallet]
 pub mod pallet {
+    use crate::weights::WeightInfo;
     use frame_support::pallet_prelude::DispatchResultWithPostInfo;
-    use frame_support::{pallet_prelude::*, traits::Get, transactional};
-    use frame_system::ensure_signed;
+    use frame_support::{
+        pallet_prelude::*,
+        traits::{Get, OnKilledAccount, Randomness},
+        transactional,
+        weights::Weight,
+        PalletId,
+    };
     use frame_system::pallet_prelude::*;
+    use frame_system::{ensure_root, ensure_signed};
     use fuso_support::traits::{Rewarding, Token};
+    use sp_core::U256;
     use sp_runtime::{
-        traits::{CheckedAdd, Zero},
-        DispatchError, DispatchResult, Perquintill,
+        traits::{
+            AccountIdConversion, AtLeast32BitUnsigned, Bounded, CheckedAdd, CheckedSub, Convert,
+            MaybeSerializeDeserialize, One, Saturating, TrailingZeroInput, UniqueSaturatedInto,
+            Zero,
+        },
+        DispatchError, DispatchResult, PerThing, Perquintill,
     };
-    use sp_std::result::Result;
+    use sp_std::{collections::btree_map::BTreeMap, result::Result, vec::Vec};
 
-    pub type Volume<T> =
-        <<T as Config>::Asset as Token<<T as frame_system::Config>::AccountId>>::Balance;
+    pub type Volume<T> = <T as Config>::Volume;
 
     pub type Balance<T> =
         <<T as Config>::Asset as Token<<T as frame_system::Config>::AccountId>>::Balance;
 
     pub type Era<T> = <T as frame_system::Config>::BlockNumber;
 
+    pub type TokenId<T> =
+        <<T as Config>::Asset as Token<<T as frame_system::Config>::AccountId>>::TokenId;
+
     #[pallet::config]
     pub trait Config: frame_system::Config {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 
+        /// how the recorded volumes accrue rewards, `EraRotationAccrual` or the MasterChef-style
+        /// `SharedPoolAccrual` unless a chain supplies its own model
+        type AccrualModel: Accrual<Self>;
+
         type Asset: Token<Self::AccountId>;
 
         #[pallet::constant]
@@ -51,12 +74,292 @@ pub mod pallet {
 
         #[pallet::constant]
         type RewardsPerEra: Get<Balance<Self>>;
+
+        /// distribute the whole era pool using the largest-remainder method instead of
+        /// truncating each share, the participants are iterated on the first settlement
+        #[pallet::constant]
+        type ExactDistribution: Get<bool>;
+
+        /// eras with more participants than this fall back to the truncated shares
+        #[pallet::constant]
+        type MaxParticipants: Get<u32>;
+
+        /// credit the claimed rewards to the reserved balance, e.g. for governance locks
+        #[pallet::constant]
+        type ClaimAsReserved: Get<bool>;
+
+        /// blocks after each era boundary within which `take_reward` is fee-free
+        #[pallet::constant]
+        type FreeClaimWindow: Get<Self::BlockNumber>;
+
+        /// the cut of each confirmed reward moved to the referrer of the trader
+        #[pallet::constant]
+        type ReferralShare: Get<Perquintill>;
+
+        /// reject the volumes reported at a block later than the current one
+        #[pallet::constant]
+        type StrictTimestamps: Get<bool>;
+
+        /// the number of closed eras whose `EraRewards` are retained
+        #[pallet::constant]
+        type HistoryDepth: Get<u32>;
+
+        /// the minimal blocks between two claims of an account
+        #[pallet::constant]
+        type MinClaimInterval: Get<Self::BlockNumber>;
+
+        /// the claimable rewards above this bypass `MinClaimInterval`
+        #[pallet::constant]
+        type LargeClaimThreshold: Get<Balance<Self>>;
+
+        /// the number of eras reported together as an epoch
+        #[pallet::constant]
+        type ErasPerEpoch: Get<u32>;
+
+        /// the hard cap of all the rewards ever confirmed
+        #[pallet::constant]
+        type MaxTotalEmission: Get<Balance<Self>>;
+
+        /// the native balance reserved by `register_for_rewards` until the first claim
+        #[pallet::constant]
+        type RegistrationDeposit: Get<Balance<Self>>;
+
+        type WeightInfo: WeightInfo;
+
+        /// ignore the volume of the pairs trading the reward token itself
+        #[pallet::constant]
+        type ExcludeNativeTrades: Get<bool>;
+
+        /// the maximal entries of a volume snapshot imported at once
+        #[pallet::constant]
+        type MaxImport: Get<u32>;
+
+        /// raffle the rounding dust of an exact distribution to a single participant picked
+        /// with the probability of its volume share instead of the largest remainders
+        #[pallet::constant]
+        type DustLottery: Get<bool>;
+
+        /// the on-chain randomness deciding the winner of the dust lottery
+        type Randomness: Randomness<Self::Hash, Self::BlockNumber>;
+
+        /// buffer the trades of a block and apply them in `on_finalize`, so an account trading
+        /// many times in a block rotates only once
+        #[pallet::constant]
+        type BatchSettlement: Get<bool>;
+
+        /// the floor of the pool of every era so the emission keeps sustaining the trading
+        #[pallet::constant]
+        type MinEraReward: Get<Balance<Self>>;
+
+        /// the maximal accounts queried by `pending_rewards_batch`
+        #[pallet::constant]
+        type MaxBatchQuery: Get<u32>;
+
+        /// the maximal eras replayed by `emit_claim_history` at once
+        #[pallet::constant]
+        type MaxHistoryRange: Get<u32>;
+
+        /// the account running the matcher which submits the volumes
+        #[pallet::constant]
+        type OperatorAccount: Get<Self::AccountId>;
+
+        /// the cut of each era pool credited to the operator when the era closes
+        #[pallet::constant]
+        type OperatorShare: Get<Perquintill>;
+
+        /// the category of the independent pools, e.g. spot or perpetual trading
+        type PoolId: Parameter + Member + Copy;
+
+        /// the pool of each traded token, so the emission of a token is split only among the
+        /// traders of that token
+        type TokenPool: Convert<TokenId<Self>, Self::PoolId>;
+
+        /// the trading pair a volume is attributed to for reporting
+        type PairId: Parameter + Member + Copy;
+
+        /// the volume traded, independent of the balance of the reward token, e.g. a
+        /// fixed-point amount of the quote currency
+        type Volume: Member
+            + Parameter
+            + AtLeast32BitUnsigned
+            + Default
+            + Copy
+            + MaybeSerializeDeserialize;
+
+        /// the lossless conversion of the volumes for the reward math, `ConvertInto` unless
+        /// the volume is a custom fixed-point type
+        type VolumeToU128: Convert<Volume<Self>, u128>;
+
+        /// the conversion back of the volumes computed in the reward math, saturating at the
+        /// bounds of the volume
+        type U128ToVolume: Convert<u128, Volume<Self>>;
+
+        /// a single trade recording more volume than this is flagged for monitoring
+        #[pallet::constant]
+        type LargeVolumeThreshold: Get<Volume<Self>>;
+
+        /// the maximal emission schedules running at once
+        #[pallet::constant]
+        type MaxSchedules: Get<u32>;
+
+        /// the fills of `save_trading_pair` whose maker is the taker only count in the totals
+        #[pallet::constant]
+        type ExcludeSelfTrades: Get<bool>;
+
+        /// the origin recording the immutable snapshots of the closed eras
+        type KeeperOrigin: EnsureOrigin<Self::Origin>;
+
+        /// the origin reporting the volumes through `report_volume`
+        type VolumeReporter: EnsureOrigin<Self::Origin>;
+
+        /// the maximal accounts granted a virtual volume
+        #[pallet::constant]
+        type MaxVirtualAccounts: Get<u32>;
+
+        /// the extra volume granted to the first trade of an account in each era, the boost
+        /// is shared out of the era pool which the bonus pools top up. The virtual volume is
+        /// no trade and is never boosted
+        #[pallet::constant]
+        type EarlyBirdMultiplier: Get<Perquintill>;
+
+        /// the claims below this are rejected, the rewards stay confirmed until they grow
+        #[pallet::constant]
+        type MinClaimable: Get<Balance<Self>>;
+
+        /// the depth of the referrer chain earning from a reward, 1 for the direct referrer only
+        #[pallet::constant]
+        type ReferralLevels: Get<u32>;
+
+        /// the cut of each level above the direct referrer, which earns `ReferralShare`
+        #[pallet::constant]
+        type ReferralLevelShares: Get<Vec<Perquintill>>;
+
+        /// the cap of the cuts of all the levels together
+        #[pallet::constant]
+        type MaxReferralShare: Get<Perquintill>;
+
+        /// the part of a claim credited to the free balance right away, the rest is reserved
+        /// and vests linearly over `VestingPeriod`
+        #[pallet::constant]
+        type InstantShare: Get<Perquintill>;
+
+        /// the blocks over which the vested part of a claim is released
+        #[pallet::constant]
+        type VestingPeriod: Get<Self::BlockNumber>;
+
+        /// the volume the trades reported in a single block can record, saving more is
+        /// rejected until the next block. The volume credited by root, i.e. imported or
+        /// virtual, is not a trade and is not capped
+        #[pallet::constant]
+        type MaxVolumePerBlock: Get<Volume<Self>>;
+
+        /// every confirmation is rounded down to a multiple of this unit, e.g. 1 $TAO, zero or
+        /// one disables the rounding
+        #[pallet::constant]
+        type RoundToUnit: Get<Balance<Self>>;
+
+        /// draw the emission out of the funds added by `fund_pool` instead of minting it
+        #[pallet::constant]
+        type FundedEmission: Get<bool>;
+
+        /// the id deriving the account holding the funds of the pool
+        #[pallet::constant]
+        type PalletId: Get<PalletId>;
+
+        /// reject a `take_reward` with nothing confirmed but the volume of the open era pending
+        /// instead of paying zero
+        #[pallet::constant]
+        type StrictClaims: Get<bool>;
+
+        /// the entries of one page of `export_state` and `export_volumes`
+        #[pallet::constant]
+        type MaxExportPage: Get<u32>;
+
+        /// weight the volume of every account in a closed era by the blocks from its first to
+        /// its last trade of the era, for eras of up to `MaxParticipants` participants
+        #[pallet::constant]
+        type ActiveBlockWeighting: Get<bool>;
+
+        /// the floor of the total volume an era is shared by, so a lone trader of a quiet era
+        /// takes `pending_vol / MinDenominator` of the pool and the rest is left in the emission
+        #[pallet::constant]
+        type MinDenominator: Get<u128>;
+
+        /// the cut of every claim burned instead of credited
+        #[pallet::constant]
+        type ClaimBurn: Get<Perquintill>;
+
+        /// the entries of the expired eras `on_initialize` removes per block at most, counting
+        /// every era passed as one
+        #[pallet::constant]
+        type MaxPrunePerBlock: Get<u32>;
+
+        /// the supply of the reward token, counting the rewards confirmed for minting, above
+        /// which the final pending eras are deferred instead of settled
+        #[pallet::constant]
+        type MaxIssuance: Get<Balance<Self>>;
+
+        /// move the volume overflowing the total of its era to the next one, which takes all
+        /// the volume of the rest of the window, instead of rejecting it
+        #[pallet::constant]
+        type VolumeRollover: Get<bool>;
+
+        /// keep the share of the era volume each confirmation is computed from, for the
+        /// retained eras
+        #[pallet::constant]
+        type RecordShares: Get<bool>;
     }
 
     #[pallet::event]
     #[pallet::generate_deposit(pub (super) fn deposit_event)]
     pub enum Event<T: Config> {
         RewardClaimed(T::AccountId, Balance<T>),
+        ZeroRateEra(Era<T>),
+        RewardInspected(T::AccountId, Balance<T>, Volume<T>, Era<T>, Balance<T>),
+        ReferrerBound(T::AccountId, T::AccountId),
+        EmissionCapReached,
+        SubAccountParentSet(T::AccountId, Option<T::AccountId>),
+        RewardConfirmed(T::AccountId, Balance<T>, u64),
+        VolumeRecorded(T::AccountId, Volume<T>, u64),
+        VolumeRevoked(T::AccountId, Volume<T>),
+        /// era, total volume, participants, the pool allotted to them which is confirmed
+        /// lazily as they settle
+        EraClosed(Era<T>, Volume<T>, u32, Balance<T>),
+        Registered(T::AccountId, Balance<T>),
+        PoolRewardsPerEraSet(T::PoolId, Balance<T>),
+        PoolRewardClaimed(T::PoolId, T::AccountId, Balance<T>),
+        VolumeSnapshotImported(u32),
+        KycHoldSet(T::AccountId),
+        KycHoldCleared(T::AccountId),
+        EraDonated(T::AccountId, Era<T>, Balance<T>),
+        EraDurationChanged(T::BlockNumber, T::BlockNumber),
+        EraRewardEntry(T::AccountId, Era<T>, Balance<T>),
+        OperatorCredited(T::AccountId, Era<T>, Balance<T>),
+        RewardLocked(T::AccountId, Balance<T>, T::BlockNumber),
+        RewardUnlocked(T::AccountId, Balance<T>),
+        LargeVolumeObserved(T::AccountId, Volume<T>, Era<T>),
+        RewardForfeited(T::AccountId, Balance<T>),
+        EmissionScheduled(Era<T>, Era<T>, Balance<T>),
+        BoostScheduled(T::BlockNumber, T::BlockNumber, Perquintill),
+        SelfTradeExcluded(T::AccountId, Volume<T>),
+        EraSnapshotted(Era<T>, Volume<T>, u32),
+        VirtualVolumeSet(T::AccountId, Volume<T>),
+        EraReconciled(Era<T>, Volume<T>, Volume<T>),
+        PendingVolumeTransferred(T::AccountId, T::AccountId, Volume<T>),
+        CrankingOptedIn(T::AccountId, Option<Perquintill>),
+        CrankTipPaid(T::AccountId, T::AccountId, Balance<T>),
+        Vested(T::AccountId, Balance<T>),
+        VolumeCheckpointConfirmed(Era<T>, Volume<T>),
+        RewardBurned(T::AccountId, Balance<T>),
+        ParamsUpdated(ParamUpdate<Balance<T>, T::BlockNumber>),
+        PoolFunded(T::AccountId, Balance<T>),
+        PoolDepleted(Balance<T>),
+        AccrualSuspendedForSupply(T::AccountId, Balance<T>),
+        EraRolledOver(Era<T>, Era<T>),
+        /// sub-account, the parent to accept it
+        SubAccountRequested(T::AccountId, T::AccountId),
+        /// the volume of an era older than the retained ones, settled as nothing
+        RewardExpired(T::AccountId, Volume<T>, u64),
     }
 
     #[pallet::error]
@@ -64,155 +367,3378 @@ pub mod pallet {
         Overflow,
         DivideByZero,
         RewardNotFound,
+        SelfReferral,
+        ReferrerAlreadyBound,
+        FutureEra,
+        EraFinalized,
+        AttributionLoop,
+        SubAccountNotRequested,
+        NotSubAccount,
+        ClaimTooFrequent,
+        AlreadyRegistered,
+        InsufficientBalance,
+        MisalignedEra,
+        DuplicateEntry,
+        KycRequired,
+        EraAlreadyClosed,
+        InvalidEraDuration,
+        RangeTooLong,
+        EraNotRetained,
+        LockNotFound,
+        RewardStillLocked,
+        InvalidSchedule,
+        TooManySchedules,
+        EraNotClosed,
+        AlreadySnapshotted,
+        TooManyParticipants,
+        TooManyVirtualAccounts,
+        BelowMinClaim,
+        SelfTransfer,
+        InsufficientVolume,
+        NotCrankable,
+        TipTooHigh,
+        BlockVolumeCapExceeded,
+        CheckpointMismatch,
     }
 
     #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T>
+    where
+        Balance<T>: From<u128>,
+    {
+        fn on_initialize(now: T::BlockNumber) -> Weight {
+            // the buffer is always flushed by `on_finalize`, never carry trades across blocks
+            let mut weight: Weight = T::DbWeight::get().reads(1);
+            if TradeBuffer::<T>::exists() {
+                Self::flush_trades();
+                weight = weight.saturating_add(T::DbWeight::get().writes(1));
+            }
+            BlockVolume::<T>::kill();
+            weight = weight.saturating_add(T::DbWeight::get().writes(1));
+            weight = weight.saturating_add(Self::prune_expired(now));
+            if now.is_zero() || Self::era_of(now).start() != now {
+                return weight;
+            }
+            let closed = Self::era_of(now - One::one()).start();
+            Self::freeze_params(closed);
+            for pool_id in KnownPools::<T>::iter_keys() {
+                Self::freeze_pool_rate(pool_id, closed);
+            }
+            for (who, vol) in VirtualVolume::<T>::iter() {
+                if T::ActiveBlockWeighting::get() {
+                    Self::extend_span(now, &who, Self::era_span(now));
+                }
+                let _ = Self::apply_trading(&who, vol, now);
+                weight = weight.saturating_add(T::WeightInfo::save_trading_rotate());
+            }
+            let total_vol = Self::volumes(Self::era_of(closed));
+            let allotted = if total_vol.is_zero() {
+                Zero::zero()
+            } else {
+                // the cut of the operator is paid right away, once per era
+                if !Self::operator_paid(closed) {
+                    let _ = Self::credit_operator(closed);
+                    weight = weight.saturating_add(T::DbWeight::get().writes(2));
+                }
+                Self::traders_pool(closed)
+            };
+            weight = weight.saturating_add(T::AccrualModel::on_era_closed(closed));
+            Self::deposit_event(Event::EraClosed(
+                closed,
+                total_vol,
+                Self::participants(closed),
+                allotted,
+            ));
+            // a zero rate accrues nothing, warn operators once the era closes
+            if Self::era_reward_at(closed).is_zero() {
+                Self::deposit_event(Event::ZeroRateEra(closed));
+            }
+            weight.saturating_add(T::DbWeight::get().reads(2))
+        }
+
+        fn on_finalize(_now: T::BlockNumber) {
+            if TradeBuffer::<T>::exists() {
+                Self::flush_trades();
+            }
+        }
+    }
 
+    /// an era-aligned block number, only built by `era_of` so that an unaligned block can't be
+    /// passed where an era is expected
+    ///
+    /// ```compile_fail
+    /// let era: pallet_fuso_reward::EraId<u32> = 150u32;
+    /// ```
+    ///
+    /// ```compile_fail
+    /// let era = pallet_fuso_reward::EraId(150u32);
+    /// ```
+    #[derive(
+        Clone, Copy, Encode, Decode, Eq, PartialEq, Ord, PartialOrd, RuntimeDebug, TypeInfo,
+    )]
+    pub struct EraId<BlockNumber>(BlockNumber);
+
+    impl<BlockNumber: Copy> EraId<BlockNumber> {
+        /// the first block of the era
+        pub fn start(&self) -> BlockNumber {
+            self.0
+        }
+    }
+
+    /// the precision of `AccRewardPerShare`
+    pub const ACC_REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+    /// the cumulative volume of an account in the shared pool engine and the accrued reward
+    /// already accounted to it
     #[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, Default)]
-    pub struct Reward<Balance, Volume, Era> {
-        pub confirmed: Balance,
-        pub pending_vol: Volume,
-        pub last_modify: Era,
+    pub struct ShareInfo<Volume, Balance> {
+        pub shares: Volume,
+        pub reward_debt: Balance,
     }
 
-    #[pallet::storage]
-    #[pallet::getter(fn rewards)]
-    pub type Rewards<T: Config> = StorageMap<
-        _,
-        Blake2_128Concat,
-        T::AccountId,
-        Reward<Balance<T>, Volume<T>, Era<T>>,
-        ValueQuery,
-    >;
+    /// the total volume and the volumes of the participants of a closed era as recorded by
+    /// `snapshot_era`
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, Default)]
+    pub struct EraSnapshot<AccountId, Volume> {
+        pub total_vol: Volume,
+        pub participants: Vec<(AccountId, Volume)>,
+    }
 
-    #[pallet::storage]
-    #[pallet::getter(fn volumes)]
-    pub type Volumes<T: Config> = StorageMap<_, Blake2_128Concat, Era<T>, Volume<T>, ValueQuery>;
+    /// the claimed rewards vesting linearly from `start` to `end`, of which `released` are
+    /// unreserved
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, Default)]
+    pub struct VestingInfo<Balance, BlockNumber> {
+        pub total: Balance,
+        pub released: Balance,
+        pub start: BlockNumber,
+        pub end: BlockNumber,
+    }
 
-    #[pallet::pallet]
-    #[pallet::generate_store(pub (super) trait Store)]
-    pub struct Pallet<T>(_);
+    /// the accrual of the rewards to the volumes, while the totals of the eras are kept by
+    /// the pallet whatever the model is
+    pub trait Accrual<T: Config> {
+        /// accrue `vol` traded by `who` in `era`
+        fn record(who: &T::AccountId, vol: Volume<T>, era: EraId<T::BlockNumber>)
+            -> DispatchResult;
 
-    #[pallet::call]
-    impl<T: Config> Pallet<T>
-    where
-        Volume<T>: Into<u128>,
-        Balance<T>: From<u128>,
-    {
-        #[pallet::weight(10000000)]
-        pub fn take_reward(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
-            let who = ensure_signed(origin)?;
-            let at = frame_system::Pallet::<T>::block_number();
-            let reward = Self::claim_reward(&who, at)?;
-            Self::deposit_event(Event::RewardClaimed(who, reward));
-            Ok(().into())
+        /// confirm what `who` accrued before `era` and return its confirmed rewards
+        fn settle(
+            who: &T::AccountId,
+            era: EraId<T::BlockNumber>,
+        ) -> Result<Balance<T>, DispatchError>;
+
+        /// the rewards `who` could claim now
+        fn claimable(who: &T::AccountId) -> Balance<T>;
+
+        /// remove at most `max` of the rewards confirmed by `settle` and return them
+        fn take(who: &T::AccountId, max: Balance<T>) -> Result<Balance<T>, DispatchError>;
+
+        /// remove up to `vol` accrued by `who` in the open `era` and return the removed
+        fn withdraw(
+            who: &T::AccountId,
+            vol: Volume<T>,
+            era: EraId<T::BlockNumber>,
+        ) -> Result<Volume<T>, DispatchError>;
+
+        /// hook of the close of `era`, before any account is settled in the next one
+        fn on_era_closed(_era: Era<T>) -> Weight {
+            0
         }
     }
 
-    impl<T: Config> Pallet<T>
+    /// every account keeps the volume of its latest era pending, which is confirmed as its
+    /// share of the era pool once the account is touched in a later era
+    pub struct EraRotationAccrual;
+
+    impl<T: Config> Accrual<T> for EraRotationAccrual
     where
-        Volume<T>: Into<u128>,
         Balance<T>: From<u128>,
     {
-        #[transactional]
-        fn claim_reward(
+        fn record(
             who: &T::AccountId,
-            at: T::BlockNumber,
+            vol: Volume<T>,
+            era: EraId<T::BlockNumber>,
+        ) -> DispatchResult {
+            Pallet::<T>::rotate_reward(era, vol, who).map(|_| ())
+        }
+
+        fn settle(
+            who: &T::AccountId,
+            era: EraId<T::BlockNumber>,
         ) -> Result<Balance<T>, DispatchError> {
-            let at = at - at % Self::era_duration();
-            let confirmed = Self::rotate_reward(at, Zero::zero(), &who)?;
-            if confirmed == Zero::zero() {
-                return Ok(Zero::zero());
+            Pallet::<T>::rotate_reward(era, Zero::zero(), who)
+        }
+
+        /// the confirmed rewards plus the reward of the pending and the staged volumes whose
+        /// eras are closed but not settled yet
+        fn claimable(who: &T::AccountId) -> Balance<T> {
+            let reward = Pallet::<T>::rewards(who);
+            let now = frame_system::Pallet::<T>::block_number();
+            let confirmed = reward
+                .confirmed
+                .saturating_add(Pallet::<T>::staged_reward(who));
+            if Pallet::<T>::is_era_closed(reward.last_modify, now) {
+                confirmed.saturating_add(Pallet::<T>::projected_reward(who))
+            } else {
+                confirmed
             }
-            Rewards::<T>::try_mutate_exists(who, |r| -> Result<Balance<T>, DispatchError> {
-                ensure!(r.is_some(), Error::<T>::RewardNotFound);
-                let mut reward: Reward<Balance<T>, Volume<T>, Era<T>> = r.take().unwrap();
-                let confirmed = reward.confirmed;
-                reward.confirmed = Zero::zero();
-                if reward.pending_vol > Zero::zero() {
-                    r.replace(reward);
-                }
-                if confirmed > Zero::zero() {
-                    T::Asset::try_mutate_account(&T::Asset::native_token_id(), &who, |b| {
-                        Ok(b.0 += confirmed)
-                    })?;
-                }
-                Ok(confirmed)
-            })
         }
 
-        #[transactional]
-        fn rotate_reward(
-            at: T::BlockNumber,
+        fn take(who: &T::AccountId, max: Balance<T>) -> Result<Balance<T>, DispatchError> {
+            Ok(Pallet::<T>::take_confirmed(who, max))
+        }
+
+        fn withdraw(
+            who: &T::AccountId,
             vol: Volume<T>,
-            account: &T::AccountId,
-        ) -> Result<Balance<T>, DispatchError> {
-            Rewards::<T>::try_mutate(account, |r| -> Result<Balance<T>, DispatchError> {
-                if at == r.last_modify {
-                    r.pending_vol = r
-                        .pending_vol
-                        .checked_add(&vol)
-                        .ok_or(Error::<T>::Overflow)?;
-                    Ok(r.confirmed)
-                } else {
-                    if r.pending_vol == Zero::zero() {
-                        r.pending_vol = vol;
-                        r.last_modify = at;
-                    } else {
-                        let pending_vol: u128 = r.pending_vol.into();
-                        let total_vol: u128 = Volumes::<T>::get(r.last_modify).into();
-                        ensure!(total_vol > 0, Error::<T>::DivideByZero);
-                        let p: Perquintill = Perquintill::from_rational(pending_vol, total_vol);
-                        let era_reward: u128 = T::RewardsPerEra::get().into();
-                        let a = p * era_reward;
-                        r.confirmed = r
-                            .confirmed
-                            .checked_add(&a.into())
-                            .ok_or(Error::<T>::Overflow)?;
-                        r.pending_vol = vol;
-                        r.last_modify = at;
-                    }
-                    Ok(r.confirmed)
+            era: EraId<T::BlockNumber>,
+        ) -> Result<Volume<T>, DispatchError> {
+            let removed = Rewards::<T>::mutate(who, |r| {
+                if r.last_modify != era.start() {
+                    return Zero::zero();
                 }
-            })
+                let removed = vol.min(r.pending_vol);
+                r.pending_vol -= removed;
+                removed
+            });
+            Pallet::<T>::adjust_pending(removed, Zero::zero());
+            Ok(removed)
         }
     }
 
-    impl<T: Config> Rewarding<T::AccountId, Volume<T>, T::BlockNumber> for Pallet<T>
+    /// the era pools accrue MasterChef-style to the cumulative volumes as shares claimable
+    /// anytime, instead of rotating the pending volume of each account per era
+    pub struct SharedPoolAccrual;
+
+    impl<T: Config> Accrual<T> for SharedPoolAccrual
     where
-        Volume<T>: Into<u128>,
         Balance<T>: From<u128>,
     {
-        type Balance = Balance<T>;
+        fn record(
+            who: &T::AccountId,
+            vol: Volume<T>,
+            _era: EraId<T::BlockNumber>,
+        ) -> DispatchResult {
+            Pallet::<T>::deposit_shares(who, vol)
+        }
 
-        fn era_duration() -> T::BlockNumber {
-            T::EraDuration::get()
+        fn settle(
+            who: &T::AccountId,
+            _era: EraId<T::BlockNumber>,
+        ) -> Result<Balance<T>, DispatchError> {
+            Pallet::<T>::harvest_shares(who)?;
+            Ok(Pallet::<T>::rewards(who).confirmed)
         }
 
-        fn total_volume(at: T::BlockNumber) -> Volume<T> {
-            Self::volumes(at - at % Self::era_duration())
+        /// the confirmed rewards plus the pending reward of the shares
+        fn claimable(who: &T::AccountId) -> Balance<T> {
+            let info = Pallet::<T>::shares(who);
+            let reward_debt: u128 = info.reward_debt.unique_saturated_into();
+            let pending = Pallet::<T>::accrued_of(info.shares).saturating_sub(reward_debt);
+            Pallet::<T>::rewards(who)
+                .confirmed
+                .saturating_add(pending.into())
         }
 
-        fn acked_reward(who: &T::AccountId) -> Self::Balance {
-            Self::rewards(who).confirmed
+        fn take(who: &T::AccountId, max: Balance<T>) -> Result<Balance<T>, DispatchError> {
+            Ok(Pallet::<T>::take_confirmed(who, max))
         }
 
-        #[transactional]
-        fn save_trading(
-            trader: &T::AccountId,
+        /// the shares are cumulative, so at most the volume of `who` in `era` is removed
+        fn withdraw(
+            who: &T::AccountId,
             vol: Volume<T>,
-            at: T::BlockNumber,
-        ) -> DispatchResult {
-            if vol == Zero::zero() {
-                return Ok(());
-            }
-            let at = at - at % Self::era_duration();
-            Volumes::<T>::try_mutate(&at, |v| -> DispatchResult {
-                Ok(*v = v.checked_add(&vol).ok_or(Error::<T>::Overflow)?)
-            })?;
-            Self::rotate_reward(at, vol, trader)?;
-            Ok(())
+            era: EraId<T::BlockNumber>,
+        ) -> Result<Volume<T>, DispatchError> {
+            Pallet::<T>::harvest_shares(who)?;
+            let removed = Shares::<T>::mutate(who, |info| {
+                let removed = vol
+                    .min(info.shares)
+                    .min(Pallet::<T>::era_volumes(era.start(), who));
+                info.shares -= removed;
+                info.reward_debt = Pallet::<T>::accrued_of(info.shares).into();
+                removed
+            });
+            TotalShares::<T>::mutate(|total| *total = total.saturating_sub(removed));
+            Ok(removed)
+        }
+
+        fn on_era_closed(era: Era<T>) -> Weight {
+            let _ = Pallet::<T>::accrue_shared_pool(era);
+            T::DbWeight::get().writes(2)
+        }
+    }
+
+    /// the state of the rewards of an account as told by `account_status`
+    #[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+    pub enum RewardStatus {
+        /// no trade was ever recorded
+        NeverTraded,
+        /// nothing confirmed yet but the volume of the latest era is pending
+        PendingOnly,
+        /// rewards are confirmed and waiting to be claimed
+        ConfirmedUnclaimed,
+        /// the account traded but holds nothing, e.g. after claiming everything
+        SettledZero,
+    }
+
+    /// the parameters changed at once by `update_params`, the absent ones are left untouched
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, Default)]
+    pub struct ParamUpdate<Balance, BlockNumber> {
+        pub era_duration: Option<BlockNumber>,
+        pub rewards_per_era: Option<Balance>,
+        pub operator_share: Option<Perquintill>,
+    }
+
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, Default)]
+    pub struct Reward<Balance, Volume, Era> {
+        pub confirmed: Balance,
+        pub pending_vol: Volume,
+        pub last_modify: Era,
+    }
+
+    #[pallet::storage]
+    #[pallet::getter(fn rewards)]
+    pub type Rewards<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Reward<Balance<T>, Volume<T>, Era<T>>,
+        ValueQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn volumes)]
+    pub type Volumes<T: Config> =
+        StorageMap<_, Blake2_128Concat, EraId<T::BlockNumber>, Volume<T>, ValueQuery>;
+
+    /// the sum of the `pending_vol` of all the accounts
+    #[pallet::storage]
+    #[pallet::getter(fn total_pending_volume)]
+    pub type TotalPendingVolume<T: Config> = StorageValue<_, Volume<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn total_distributed)]
+    pub type TotalDistributed<T: Config> = StorageValue<_, Balance<T>, ValueQuery>;
+
+    /// the durations set by the governance in order, each with the era boundary its eras are
+    /// aligned from, only the last one may not be in effect yet. Before the first one the eras
+    /// are the `EraDuration` long from the genesis.
+    #[pallet::storage]
+    #[pallet::getter(fn era_alignments)]
+    pub type EraAlignments<T: Config> =
+        StorageValue<_, Vec<(T::BlockNumber, T::BlockNumber)>, ValueQuery>;
+
+    /// the oldest era whose data isn't pruned yet
+    #[pallet::storage]
+    #[pallet::getter(fn prune_cursor)]
+    pub type PruneCursor<T: Config> = StorageValue<_, Era<T>, ValueQuery>;
+
+    /// the eras whose total would have overflowed, closed to any further volume
+    #[pallet::storage]
+    #[pallet::getter(fn rolled_over_eras)]
+    pub type RolledOverEras<T: Config> = StorageMap<_, Blake2_128Concat, Era<T>, bool, ValueQuery>;
+
+    /// the pending volumes moved out of their era before it closed, confirmed from the final
+    /// total of the era once it does
+    #[pallet::storage]
+    #[pallet::getter(fn staged_volumes)]
+    pub type StagedVolumes<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        Era<T>,
+        Volume<T>,
+        ValueQuery,
+    >;
+
+    /// the excluded self-trade volumes counted in the total of their era but in no `EraVolumes`
+    #[pallet::storage]
+    #[pallet::getter(fn self_trade_volumes)]
+    pub type SelfTradeVolumes<T: Config> =
+        StorageMap<_, Blake2_128Concat, Era<T>, Volume<T>, ValueQuery>;
+
+    /// the raw key of the last `EraVolumes` entry summed by an unfinished `reconcile_era` and
+    /// the sum so far
+    #[pallet::storage]
+    #[pallet::getter(fn reconcile_progress)]
+    pub type ReconcileProgress<T: Config> =
+        StorageMap<_, Blake2_128Concat, Era<T>, (Vec<u8>, Volume<T>), OptionQuery>;
+
+    /// the `RewardsPerEra` set by the governance
+    #[pallet::storage]
+    #[pallet::getter(fn rewards_per_era_override)]
+    pub type RewardsPerEraOverride<T: Config> = StorageValue<_, Balance<T>, OptionQuery>;
+
+    /// the `OperatorShare` set by the governance
+    #[pallet::storage]
+    #[pallet::getter(fn operator_share_override)]
+    pub type OperatorShareOverride<T: Config> = StorageValue<_, Perquintill, OptionQuery>;
+
+    /// the `RewardsPerEra` and the `OperatorShare` of each era frozen once it's final, so the
+    /// traders and the operator share the same pool
+    #[pallet::storage]
+    #[pallet::getter(fn era_params)]
+    pub type EraParams<T: Config> =
+        StorageMap<_, Blake2_128Concat, Era<T>, (Balance<T>, Perquintill), OptionQuery>;
+
+    /// the closed eras whose operator cut has been credited
+    #[pallet::storage]
+    #[pallet::getter(fn operator_paid)]
+    pub type OperatorPaid<T: Config> = StorageMap<_, Blake2_128Concat, Era<T>, bool, ValueQuery>;
+
+    /// the pools of the closed eras accumulated per share of the shared pool engine
+    #[pallet::storage]
+    #[pallet::getter(fn acc_reward_per_share)]
+    pub type AccRewardPerShare<T: Config> = StorageValue<_, u128, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn total_shares)]
+    pub type TotalShares<T: Config> = StorageValue<_, Volume<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn shares)]
+    pub type Shares<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, ShareInfo<Volume<T>, Balance<T>>, ValueQuery>;
+
+    /// the block reported with the latest trade of each account
+    #[pallet::storage]
+    #[pallet::getter(fn last_trade_block)]
+    pub type LastTradeBlock<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::BlockNumber, OptionQuery>;
+
+    /// the donations added to the pool of each era
+    #[pallet::storage]
+    #[pallet::getter(fn era_bonus_pool)]
+    pub type EraBonusPool<T: Config> =
+        StorageMap<_, Blake2_128Concat, Era<T>, Balance<T>, ValueQuery>;
+
+    /// the confirmed rewards withdrawn by `take_reward` and `take_pool_reward`
+    #[pallet::storage]
+    #[pallet::getter(fn total_claimed)]
+    pub type TotalClaimed<T: Config> = StorageValue<_, Balance<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn vesting)]
+    pub type Vesting<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        VestingInfo<Balance<T>, T::BlockNumber>,
+        OptionQuery,
+    >;
+
+    /// the maximal tip an account accepts to pay the keepers claiming for it
+    #[pallet::storage]
+    #[pallet::getter(fn crank_max_tip)]
+    pub type CrankMaxTip<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Perquintill, OptionQuery>;
+
+    /// the volume accrued to an account at the start of every era without trading, e.g. for
+    /// running the infrastructure, active all along the era under `ActiveBlockWeighting`
+    #[pallet::storage]
+    #[pallet::getter(fn virtual_volume)]
+    pub type VirtualVolume<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Volume<T>, ValueQuery>;
+
+    /// the snapshots written once by `snapshot_era`, never altered afterwards
+    #[pallet::storage]
+    #[pallet::getter(fn era_snapshots)]
+    pub type EraSnapshots<T: Config> =
+        StorageMap<_, Blake2_128Concat, Era<T>, EraSnapshot<T::AccountId, Volume<T>>, OptionQuery>;
+
+    /// the bonus added to the pool of every era from the first to the last era inclusively
+    #[pallet::storage]
+    #[pallet::getter(fn emission_schedules)]
+    pub type EmissionSchedules<T: Config> =
+        StorageValue<_, Vec<(Era<T>, Era<T>, Balance<T>)>, ValueQuery>;
+
+    /// the extra volume credited to the trades from the first to the last block inclusively,
+    /// regardless of the era boundaries
+    #[pallet::storage]
+    #[pallet::getter(fn boost_windows)]
+    pub type BoostWindows<T: Config> =
+        StorageValue<_, Vec<(T::BlockNumber, T::BlockNumber, Perquintill)>, ValueQuery>;
+
+    /// the trades of the current block waiting for `on_finalize` in batch settlement mode,
+    /// flagged when they are excluded self-trades only counting in the era total
+    #[pallet::storage]
+    #[pallet::getter(fn trade_buffer)]
+    pub type TradeBuffer<T: Config> =
+        StorageValue<_, Vec<(T::AccountId, Volume<T>, Era<T>, bool)>, ValueQuery>;
+
+    /// the remainders of the confirmations of each era rounded to `RoundToUnit`, which are
+    /// left in the emission
+    #[pallet::storage]
+    #[pallet::getter(fn era_dust)]
+    pub type EraDust<T: Config> = StorageMap<_, Blake2_128Concat, Era<T>, Balance<T>, ValueQuery>;
+
+    /// the funds of the pool account left for the funded emission
+    #[pallet::storage]
+    #[pallet::getter(fn pool_balance)]
+    pub type PoolBalance<T: Config> = StorageValue<_, Balance<T>, ValueQuery>;
+
+    /// the volume recorded in the current block, reset by `on_initialize`
+    #[pallet::storage]
+    #[pallet::getter(fn block_volume)]
+    pub type BlockVolume<T: Config> = StorageValue<_, Volume<T>, ValueQuery>;
+
+    /// the era pools confirmed within each epoch
+    #[pallet::storage]
+    #[pallet::getter(fn epoch_distributed)]
+    pub type EpochDistributed<T: Config> = StorageMap<_, Twox64Concat, u64, Balance<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn last_claim)]
+    pub type LastClaim<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::BlockNumber, OptionQuery>;
+
+    /// the deposits reserved by the registered accounts which haven't claimed yet
+    #[pallet::storage]
+    #[pallet::getter(fn registrations)]
+    pub type Registrations<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, Balance<T>, OptionQuery>;
+
+    /// the claimed rewards kept reserved by `claim_and_lock` and the block they unlock at
+    #[pallet::storage]
+    #[pallet::getter(fn reward_locks)]
+    pub type RewardLocks<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, (Balance<T>, T::BlockNumber), OptionQuery>;
+
+    /// the accounts whose claims are held until the KYC passes, they still accrue rewards
+    #[pallet::storage]
+    #[pallet::getter(fn kyc_hold)]
+    pub type KycHold<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, bool, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn referrer)]
+    pub type Referrer<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn sub_account_parent)]
+    pub type SubAccountParent<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+    /// the parents requested by the sub-accounts, attributed once the parent accepts
+    #[pallet::storage]
+    #[pallet::getter(fn sub_account_requests)]
+    pub type SubAccountRequests<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+    /// the sub-accounts attributing their volume to each parent
+    #[pallet::storage]
+    pub type SubAccounts<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AccountId,
+        (),
+        ValueQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn era_volumes)]
+    pub type EraVolumes<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        Era<T>,
+        Blake2_128Concat,
+        T::AccountId,
+        Volume<T>,
+        ValueQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn era_rewards)]
+    pub type EraRewards<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        Era<T>,
+        Blake2_128Concat,
+        T::AccountId,
+        Balance<T>,
+        ValueQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn participants)]
+    pub type Participants<T: Config> = StorageMap<_, Blake2_128Concat, Era<T>, u32, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn era_distributed)]
+    pub type EraDistributed<T: Config> =
+        StorageMap<_, Blake2_128Concat, Era<T>, Balance<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn finalized_eras)]
+    pub type FinalizedEras<T: Config> = StorageMap<_, Blake2_128Concat, Era<T>, bool, ValueQuery>;
+
+    /// the first and the last block each account traded at within an era
+    #[pallet::storage]
+    #[pallet::getter(fn active_spans)]
+    pub type ActiveSpans<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        Era<T>,
+        Blake2_128Concat,
+        T::AccountId,
+        (T::BlockNumber, T::BlockNumber),
+        OptionQuery,
+    >;
+
+    /// the share of the era volume the reward of each account was confirmed from
+    #[pallet::storage]
+    #[pallet::getter(fn precise_shares)]
+    pub type PreciseShares<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        Era<T>,
+        Blake2_128Concat,
+        T::AccountId,
+        Perquintill,
+        OptionQuery,
+    >;
+
+    /// the sum of the volumes of a closed era weighted by their active blocks, computed by
+    /// its first settlement
+    #[pallet::storage]
+    #[pallet::getter(fn weighted_totals)]
+    pub type WeightedTotals<T: Config> = StorageMap<_, Blake2_128Concat, Era<T>, u128, OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn allocated_eras)]
+    pub type AllocatedEras<T: Config> = StorageMap<_, Blake2_128Concat, Era<T>, bool, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn allocations)]
+    pub type Allocations<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        Era<T>,
+        Blake2_128Concat,
+        T::AccountId,
+        Balance<T>,
+        ValueQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn pool_rewards)]
+    pub type PoolRewards<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::PoolId,
+        Blake2_128Concat,
+        T::AccountId,
+        Reward<Balance<T>, Volume<T>, Era<T>>,
+        ValueQuery,
+    >;
+
+    /// the volume of each trading pair per era, for reporting only as the rewards are still
+    /// split by the era pool
+    #[pallet::storage]
+    #[pallet::getter(fn pair_volumes)]
+    pub type PairVolumes<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::PairId,
+        Blake2_128Concat,
+        Era<T>,
+        Volume<T>,
+        ValueQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn pool_volumes)]
+    pub type PoolVolumes<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::PoolId,
+        Blake2_128Concat,
+        Era<T>,
+        Volume<T>,
+        ValueQuery,
+    >;
+
+    /// the pools which ever recorded a trade, walked to clear the pool state of an account
+    /// and to prune the volumes of the pools
+    #[pallet::storage]
+    #[pallet::getter(fn known_pools)]
+    pub type KnownPools<T: Config> = StorageMap<_, Blake2_128Concat, T::PoolId, bool, ValueQuery>;
+
+    /// the pairs which ever recorded a trade, walked to prune the volumes of the pairs
+    #[pallet::storage]
+    #[pallet::getter(fn known_pairs)]
+    pub type KnownPairs<T: Config> = StorageMap<_, Blake2_128Concat, T::PairId, bool, ValueQuery>;
+
+    /// the pending volumes of a pool moved out of their era before it closed, as
+    /// `StagedVolumes` for the default pool
+    #[pallet::storage]
+    #[pallet::getter(fn pool_staged_volumes)]
+    pub type PoolStagedVolumes<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        (T::PoolId, T::AccountId),
+        Blake2_128Concat,
+        Era<T>,
+        Volume<T>,
+        ValueQuery,
+    >;
+
+    /// the eras of each pool settled at least once, closed to any further volume
+    #[pallet::storage]
+    #[pallet::getter(fn pool_finalized_eras)]
+    pub type PoolFinalizedEras<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        Era<T>,
+        Blake2_128Concat,
+        T::PoolId,
+        bool,
+        ValueQuery,
+    >;
+
+    /// the `PoolRewardsPerEra` of each pool frozen once an era closes, so all its traders
+    /// share the same pool
+    #[pallet::storage]
+    #[pallet::getter(fn pool_era_rates)]
+    pub type PoolEraRates<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        Era<T>,
+        Blake2_128Concat,
+        T::PoolId,
+        Balance<T>,
+        OptionQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn pool_rewards_per_era)]
+    pub type PoolRewardsPerEra<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::PoolId, Balance<T>, ValueQuery>;
+
+    #[pallet::pallet]
+    #[pallet::generate_store(pub (super) trait Store)]
+    pub struct Pallet<T>(_);
+
+    impl<T: Config> Pallet<T> {
+        /// the era of the latest trade of `who`, `None` if it never traded
+        pub fn last_trade_era(who: &T::AccountId) -> Option<Era<T>> {
+            Self::last_trade_block(who).map(|at| Self::era_of(at).start())
+        }
+
+        /// create the `Rewards` entry of `who` on its first interaction with nothing pending
+        /// since the era containing `at`, so the default era 0 is never taken as a real one
+        pub fn ensure_initialized(who: &T::AccountId, at: T::BlockNumber) {
+            if !Rewards::<T>::contains_key(who) {
+                Rewards::<T>::insert(
+                    who,
+                    Reward {
+                        confirmed: Zero::zero(),
+                        pending_vol: Zero::zero(),
+                        last_modify: Self::era_of(at).start(),
+                    },
+                );
+            }
+        }
+
+        /// the account holding the funds of `fund_pool`
+        pub fn pool_account() -> T::AccountId {
+            T::PalletId::get().into_account()
+        }
+
+        /// the era containing `at`
+        pub fn era_of(at: T::BlockNumber) -> EraId<T::BlockNumber> {
+            let (from, era_duration) = Self::alignment_of(at);
+            EraId(at - (at - from) % era_duration)
+        }
+
+        /// the start of the era following the one containing `at`
+        pub fn next_era(at: T::BlockNumber) -> Era<T> {
+            let (from, era_duration) = Self::alignment_of(at);
+            at - (at - from) % era_duration + era_duration
+        }
+
+        /// the block the eras around `at` are aligned from and their duration
+        pub fn alignment_of(at: T::BlockNumber) -> (T::BlockNumber, T::BlockNumber) {
+            Self::era_alignments()
+                .into_iter()
+                .rev()
+                .find(|(from, _)| *from <= at)
+                .unwrap_or_else(|| (Zero::zero(), T::EraDuration::get()))
+        }
+
+        /// change the duration of the eras from the next era boundary on, replacing a change
+        /// not in effect yet
+        fn schedule_era_duration(era_duration: T::BlockNumber) -> DispatchResult {
+            ensure!(!era_duration.is_zero(), Error::<T>::InvalidEraDuration);
+            let now = frame_system::Pallet::<T>::block_number();
+            let (_, old) = Self::alignment_of(now);
+            let from = Self::next_era(now);
+            EraAlignments::<T>::mutate(|alignments| {
+                if matches!(alignments.last(), Some((start, _)) if *start > now) {
+                    alignments.pop();
+                }
+                alignments.push((from, era_duration));
+            });
+            Self::deposit_event(Event::EraDurationChanged(old, era_duration));
+            Ok(())
+        }
+
+        /// the pool shared by the participants of `era`, never below `MinEraReward`, plus the
+        /// donations to it and the bonuses of the schedules covering it
+        pub fn era_reward_at(era: Era<T>) -> Balance<T> {
+            Self::era_params(era)
+                .map(|(rewards_per_era, _)| rewards_per_era)
+                .unwrap_or_else(Self::rewards_per_era)
+                .max(T::MinEraReward::get())
+                .saturating_add(Self::era_bonus_pool(era))
+                .saturating_add(Self::scheduled_bonus(era))
+        }
+
+        /// the `RewardsPerEra` unless overridden by the governance
+        pub fn rewards_per_era() -> Balance<T> {
+            Self::rewards_per_era_override().unwrap_or_else(T::RewardsPerEra::get)
+        }
+
+        /// the `OperatorShare` unless overridden by the governance
+        pub fn operator_share() -> Perquintill {
+            Self::operator_share_override().unwrap_or_else(T::OperatorShare::get)
+        }
+
+        /// the `OperatorShare` of `era`, the current one until the era is final
+        pub fn operator_share_of(era: Era<T>) -> Perquintill {
+            Self::era_params(era)
+                .map(|(_, operator_share)| operator_share)
+                .unwrap_or_else(Self::operator_share)
+        }
+
+        /// freeze the current parameters for `era` unless they are already
+        fn freeze_params(era: Era<T>) {
+            if !EraParams::<T>::contains_key(era) {
+                EraParams::<T>::insert(era, (Self::rewards_per_era(), Self::operator_share()));
+            }
+        }
+
+        /// the `PoolRewardsPerEra` of `pool_id` in `era`, the current one until the era closes
+        pub fn pool_rate_of(pool_id: T::PoolId, era: Era<T>) -> Balance<T> {
+            Self::pool_era_rates(era, pool_id)
+                .unwrap_or_else(|| Self::pool_rewards_per_era(pool_id))
+        }
+
+        /// freeze the current rate of `pool_id` for `era` unless it is already
+        fn freeze_pool_rate(pool_id: T::PoolId, era: Era<T>) {
+            if !PoolEraRates::<T>::contains_key(era, pool_id) {
+                PoolEraRates::<T>::insert(era, pool_id, Self::pool_rewards_per_era(pool_id));
+            }
+        }
+
+        /// the sum of the bonuses of the overlapping schedules covering `era`
+        pub fn scheduled_bonus(era: Era<T>) -> Balance<T> {
+            Self::emission_schedules()
+                .iter()
+                .filter(|(start, end, _)| *start <= era && era <= *end)
+                .fold(Zero::zero(), |acc: Balance<T>, (_, _, bonus)| {
+                    acc.saturating_add(*bonus)
+                })
+        }
+
+        /// keep the attribution flat so it can't loop
+        fn ensure_attributable(sub: &T::AccountId, parent: &T::AccountId) -> DispatchResult {
+            ensure!(
+                sub != parent
+                    && !SubAccountParent::<T>::contains_key(parent)
+                    && SubAccounts::<T>::iter_prefix(sub).next().is_none(),
+                Error::<T>::AttributionLoop
+            );
+            Ok(())
+        }
+
+        /// attribute the volume of `sub` to itself again
+        fn detach_sub_account(sub: &T::AccountId) {
+            if let Some(old) = SubAccountParent::<T>::take(sub) {
+                SubAccounts::<T>::remove(&old, sub);
+            }
+        }
+
+        /// replace `before` of the pending volume of an account by `after` in the total
+        fn adjust_pending(before: Volume<T>, after: Volume<T>) {
+            TotalPendingVolume::<T>::mutate(|total| {
+                *total = total.saturating_sub(before).saturating_add(after)
+            });
+        }
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T>
+    where
+        Balance<T>: From<u128>,
+    {
+        /// claim the confirmed rewards along with the reward of a pending era already closed.
+        /// The volume of the open era stays pending, so an account holding nothing else is
+        /// paid zero, or rejected with `EraNotClosed` under `StrictClaims`.
+        #[pallet::weight(10000000)]
+        pub fn take_reward(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let at = frame_system::Pallet::<T>::block_number();
+            Self::ensure_claimable(&who, at)?;
+            if T::StrictClaims::get() {
+                let reward = Self::rewards(&who);
+                ensure!(
+                    !reward.confirmed.is_zero()
+                        || reward.pending_vol.is_zero()
+                        || reward.last_modify != Self::era_of(at).start(),
+                    Error::<T>::EraNotClosed
+                );
+            }
+            let reward = Self::claim_reward(&who, at, T::ClaimAsReserved::get())?;
+            if !reward.is_zero() {
+                LastClaim::<T>::insert(&who, at);
+            }
+            Self::deposit_event(Event::RewardClaimed(who, reward));
+            // claiming right after an era closes is free to reduce the outstanding rewards, as
+            // long as something is actually claimed
+            if !reward.is_zero() && at - Self::era_of(at).start() < T::FreeClaimWindow::get() {
+                Ok(Pays::No.into())
+            } else {
+                Ok(Pays::Yes.into())
+            }
+        }
+
+        /// `take_reward` paying at most `max`, the excess stays confirmed for a later claim
+        #[pallet::weight(10000000)]
+        pub fn take_reward_capped(
+            origin: OriginFor<T>,
+            max: Balance<T>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let at = frame_system::Pallet::<T>::block_number();
+            Self::ensure_claimable(&who, at)?;
+            let reward = Self::claim_reward_capped(&who, at, T::ClaimAsReserved::get(), max)?;
+            if !reward.is_zero() {
+                LastClaim::<T>::insert(&who, at);
+            }
+            Self::deposit_event(Event::RewardClaimed(who, reward));
+            Ok(().into())
+        }
+
+        /// claim the rewards into the reserved balance, which `unlock_reward` releases after
+        /// `lock_duration` blocks. A lock already held is extended to cover the new amount.
+        #[pallet::weight(10000000)]
+        pub fn claim_and_lock(
+            origin: OriginFor<T>,
+            lock_duration: T::BlockNumber,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let at = frame_system::Pallet::<T>::block_number();
+            Self::ensure_claimable(&who, at)?;
+            let reward = Self::claim_reward(&who, at, true)?;
+            if !reward.is_zero() {
+                LastClaim::<T>::insert(&who, at);
+                // the vesting part is released by `vest` only
+                let instant = Self::instant_part(reward);
+                let until = at.saturating_add(lock_duration);
+                let (locked, until) = RewardLocks::<T>::get(&who)
+                    .map(|(locked, prev)| (locked.saturating_add(instant), prev.max(until)))
+                    .unwrap_or((instant, until));
+                RewardLocks::<T>::insert(&who, (locked, until));
+                Self::deposit_event(Event::RewardLocked(who.clone(), instant, until));
+            }
+            Self::deposit_event(Event::RewardClaimed(who, reward));
+            Ok(().into())
+        }
+
+        /// release the rewards locked by `claim_and_lock` into the free balance
+        #[pallet::weight(10000000)]
+        pub fn unlock_reward(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let (locked, until) = Self::reward_locks(&who).ok_or(Error::<T>::LockNotFound)?;
+            ensure!(
+                frame_system::Pallet::<T>::block_number() >= until,
+                Error::<T>::RewardStillLocked
+            );
+            let unlocked = T::Asset::try_mutate_account(&T::Asset::native_token_id(), &who, |b| {
+                let unlocked = locked.min(b.1);
+                b.1 -= unlocked;
+                b.0 += unlocked;
+                Ok(unlocked)
+            })?;
+            RewardLocks::<T>::remove(&who);
+            Self::deposit_event(Event::RewardUnlocked(who, unlocked));
+            Ok(().into())
+        }
+
+        /// unreserve the claimed rewards of the signer vested so far
+        #[pallet::weight(10000000)]
+        pub fn vest(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let amount = Self::release_vested(&who, frame_system::Pallet::<T>::block_number())?;
+            Self::deposit_event(Event::Vested(who, amount));
+            Ok(().into())
+        }
+
+        /// allow the keepers to claim for the signer with a tip up to `max_tip`, `None` opts out
+        #[pallet::weight(10000000)]
+        pub fn opt_in_cranking(
+            origin: OriginFor<T>,
+            max_tip: Option<Perquintill>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            match max_tip {
+                Some(max_tip) => CrankMaxTip::<T>::insert(&who, max_tip),
+                None => CrankMaxTip::<T>::remove(&who),
+            }
+            Self::deposit_event(Event::CrankingOptedIn(who, max_tip));
+            Ok(().into())
+        }
+
+        /// claim for `who`, which opted in, and pay `tip` of the claimed rewards to the signer
+        #[pallet::weight(10000000)]
+        #[transactional]
+        pub fn claim_with_tip(
+            origin: OriginFor<T>,
+            who: T::AccountId,
+            tip: Perquintill,
+        ) -> DispatchResultWithPostInfo {
+            let keeper = ensure_signed(origin)?;
+            let max_tip = Self::crank_max_tip(&who).ok_or(Error::<T>::NotCrankable)?;
+            ensure!(tip <= max_tip, Error::<T>::TipTooHigh);
+            let at = frame_system::Pallet::<T>::block_number();
+            Self::ensure_claimable(&who, at)?;
+            let as_reserved = T::ClaimAsReserved::get();
+            let reward = Self::claim_reward(&who, at, as_reserved)?;
+            if reward.is_zero() {
+                Self::deposit_event(Event::RewardClaimed(who, reward));
+                return Ok(().into());
+            }
+            LastClaim::<T>::insert(&who, at);
+            // the tip is paid out of the part of the claim credited right away, never out of
+            // the balance of `who` otherwise
+            let raw: u128 = reward.unique_saturated_into();
+            let paid: Balance<T> = tip.mul_floor(raw).into();
+            let paid = paid.min(Self::instant_part(reward));
+            let native = T::Asset::native_token_id();
+            T::Asset::try_mutate_account(&native, &who, |b| -> DispatchResult {
+                let side = if as_reserved { &mut b.1 } else { &mut b.0 };
+                *side = side
+                    .checked_sub(&paid)
+                    .ok_or(Error::<T>::InsufficientBalance)?;
+                Ok(())
+            })?;
+            T::Asset::try_mutate_account(&native, &keeper, |b| -> DispatchResult {
+                b.0 = b.0.checked_add(&paid).ok_or(Error::<T>::Overflow)?;
+                Ok(())
+            })?;
+            Self::deposit_event(Event::CrankTipPaid(keeper, who.clone(), paid));
+            Self::deposit_event(Event::RewardClaimed(who, reward - paid));
+            Ok(().into())
+        }
+
+        /// request to attribute the volume of the signer to `parent`, which takes effect once
+        /// the parent accepts it by `accept_sub_account`, or attribute it to itself again with
+        /// `None`
+        #[pallet::weight(10000000)]
+        pub fn set_sub_account_parent(
+            origin: OriginFor<T>,
+            parent: Option<T::AccountId>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            match parent {
+                Some(parent) => {
+                    Self::ensure_attributable(&who, &parent)?;
+                    SubAccountRequests::<T>::insert(&who, &parent);
+                    Self::deposit_event(Event::SubAccountRequested(who, parent));
+                }
+                None => {
+                    SubAccountRequests::<T>::remove(&who);
+                    Self::detach_sub_account(&who);
+                    Self::deposit_event(Event::SubAccountParentSet(who, None));
+                }
+            }
+            Ok(().into())
+        }
+
+        /// accept the request of `sub` to attribute its volume to the signer
+        #[pallet::weight(10000000)]
+        pub fn accept_sub_account(
+            origin: OriginFor<T>,
+            sub: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let parent = ensure_signed(origin)?;
+            ensure!(
+                Self::sub_account_requests(&sub).as_ref() == Some(&parent),
+                Error::<T>::SubAccountNotRequested
+            );
+            Self::ensure_attributable(&sub, &parent)?;
+            SubAccountRequests::<T>::remove(&sub);
+            Self::detach_sub_account(&sub);
+            SubAccountParent::<T>::insert(&sub, &parent);
+            SubAccounts::<T>::insert(&parent, &sub, ());
+            Self::deposit_event(Event::SubAccountParentSet(sub, Some(parent)));
+            Ok(().into())
+        }
+
+        /// stop attributing the volume of `sub` to the signer
+        #[pallet::weight(10000000)]
+        pub fn remove_sub_account(
+            origin: OriginFor<T>,
+            sub: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let parent = ensure_signed(origin)?;
+            ensure!(
+                SubAccounts::<T>::contains_key(&parent, &sub),
+                Error::<T>::NotSubAccount
+            );
+            Self::detach_sub_account(&sub);
+            Self::deposit_event(Event::SubAccountParentSet(sub, None));
+            Ok(().into())
+        }
+
+        /// change the length of the eras from the next era boundary on, so the current era and
+        /// the closed ones keep their alignment and their totals. A change not in effect yet is
+        /// replaced.
+        #[pallet::weight(10000000)]
+        pub fn set_era_duration(
+            origin: OriginFor<T>,
+            era_duration: T::BlockNumber,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            Self::schedule_era_duration(era_duration)?;
+            Ok(().into())
+        }
+
+        /// apply all the present fields of `params` at once, each taking effect as if set by
+        /// its own extrinsic, e.g. `era_duration` like `set_era_duration`
+        #[pallet::weight(10000000)]
+        pub fn update_params(
+            origin: OriginFor<T>,
+            params: ParamUpdate<Balance<T>, T::BlockNumber>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            if let Some(era_duration) = params.era_duration {
+                Self::schedule_era_duration(era_duration)?;
+            }
+            if let Some(rewards_per_era) = params.rewards_per_era {
+                RewardsPerEraOverride::<T>::put(rewards_per_era);
+            }
+            if let Some(operator_share) = params.operator_share {
+                OperatorShareOverride::<T>::put(operator_share);
+            }
+            Self::deposit_event(Event::ParamsUpdated(params));
+            Ok(().into())
+        }
+
+        /// add `per_era_bonus` to the pool of every era from `start_era` to `end_era`, the
+        /// schedules are dropped once all of their eras are pruned, as a closed era keeps
+        /// reading its bonus until it's settled
+        #[pallet::weight(10000000)]
+        pub fn schedule_emission(
+            origin: OriginFor<T>,
+            start_era: Era<T>,
+            end_era: Era<T>,
+            per_era_bonus: Balance<T>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let start_era = Self::era_of(start_era).start();
+            let end_era = Self::era_of(end_era).start();
+            ensure!(start_era <= end_era, Error::<T>::InvalidSchedule);
+            let pruned = Self::prune_cursor();
+            EmissionSchedules::<T>::try_mutate(|schedules| -> DispatchResult {
+                schedules.retain(|(_, end, _)| *end >= pruned);
+                ensure!(
+                    (schedules.len() as u32) < T::MaxSchedules::get(),
+                    Error::<T>::TooManySchedules
+                );
+                schedules.push((start_era, end_era, per_era_bonus));
+                Ok(())
+            })?;
+            Self::deposit_event(Event::EmissionScheduled(start_era, end_era, per_era_bonus));
+            Ok(().into())
+        }
+
+        /// credit `extra` of the volume traded from `start_block` to `end_block` on top, e.g.
+        /// `100%` for a 2x window, the windows are dropped once the era of their last block is
+        /// pruned, as the volume of a closed era may still be reported until it's settled
+        #[pallet::weight(10000000)]
+        pub fn schedule_boost(
+            origin: OriginFor<T>,
+            start_block: T::BlockNumber,
+            end_block: T::BlockNumber,
+            extra: Perquintill,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            ensure!(start_block <= end_block, Error::<T>::InvalidSchedule);
+            let pruned = Self::prune_cursor();
+            BoostWindows::<T>::try_mutate(|windows| -> DispatchResult {
+                windows.retain(|(_, end, _)| Self::era_of(*end).start() >= pruned);
+                ensure!(
+                    (windows.len() as u32) < T::MaxSchedules::get(),
+                    Error::<T>::TooManySchedules
+                );
+                windows.push((start_block, end_block, extra));
+                Ok(())
+            })?;
+            Self::deposit_event(Event::BoostScheduled(start_block, end_block, extra));
+            Ok(().into())
+        }
+
+        /// accrue `vol` to `who` at the start of every era as if it traded, zero removes it
+        #[pallet::weight(10000000)]
+        pub fn set_virtual_volume(
+            origin: OriginFor<T>,
+            who: T::AccountId,
+            vol: Volume<T>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            if vol.is_zero() {
+                VirtualVolume::<T>::remove(&who);
+            } else {
+                ensure!(
+                    VirtualVolume::<T>::contains_key(&who)
+                        || (VirtualVolume::<T>::iter_keys().count() as u32)
+                            < T::MaxVirtualAccounts::get(),
+                    Error::<T>::TooManyVirtualAccounts
+                );
+                VirtualVolume::<T>::insert(&who, vol);
+            }
+            Self::deposit_event(Event::VirtualVolumeSet(who, vol));
+            Ok(().into())
+        }
+
+        /// `Rewarding::save_trading` as a transaction, for the runtimes reporting the volumes
+        /// from outside instead of from a pallet wired to this one. It is charged as settling
+        /// the pending era of the trader
+        #[pallet::weight(T::WeightInfo::save_trading_rotate())]
+        pub fn report_volume(
+            origin: OriginFor<T>,
+            trader: T::AccountId,
+            vol: Volume<T>,
+            at: T::BlockNumber,
+        ) -> DispatchResultWithPostInfo {
+            T::VolumeReporter::ensure_origin(origin)?;
+            // refund down to the same era case when nothing is settled
+            let weight =
+                <Self as Rewarding<T::AccountId, Volume<T>, T::BlockNumber>>::save_trading_weight(
+                    &trader, at,
+                );
+            <Self as Rewarding<T::AccountId, Volume<T>, T::BlockNumber>>::save_trading(
+                &trader, vol, at,
+            )?;
+            Ok(Some(weight).into())
+        }
+
+        /// move `amount` of the pending volume of the signer in the open era to `to`, the era
+        /// total is unchanged so the shares of the others are too. Under
+        /// `ActiveBlockWeighting` the span the signer was active in is merged into the one of
+        /// `to`
+        #[pallet::weight(10000000)]
+        #[transactional]
+        pub fn transfer_pending_volume(
+            origin: OriginFor<T>,
+            to: T::AccountId,
+            amount: Volume<T>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(who != to, Error::<T>::SelfTransfer);
+            let era = Self::era_of(frame_system::Pallet::<T>::block_number());
+            let at = era.start();
+            ensure!(
+                T::AccrualModel::withdraw(&who, amount, era)? == amount,
+                Error::<T>::InsufficientVolume
+            );
+            let left = Self::era_volumes(at, &who).saturating_sub(amount);
+            // the volume moved keeps the activity it was traded with
+            let span = if left.is_zero() {
+                ActiveSpans::<T>::take(at, &who)
+            } else {
+                Self::active_spans(at, &who)
+            };
+            if let Some(span) = span.filter(|_| T::ActiveBlockWeighting::get()) {
+                Self::extend_span(at, &to, span);
+            }
+            if left.is_zero() {
+                EraVolumes::<T>::remove(at, &who);
+                Participants::<T>::mutate(at, |n| *n = n.saturating_sub(1));
+            } else {
+                EraVolumes::<T>::insert(at, &who, left);
+            }
+            EraVolumes::<T>::try_mutate(at, &to, |v| -> DispatchResult {
+                if v.is_zero() {
+                    Participants::<T>::mutate(at, |n| *n = n.saturating_add(1));
+                }
+                Ok(*v = v.checked_add(&amount).ok_or(Error::<T>::Overflow)?)
+            })?;
+            T::AccrualModel::record(&to, amount, era)?;
+            Self::deposit_event(Event::PendingVolumeTransferred(who, to, amount));
+            Ok(().into())
+        }
+
+        /// reset the total volume of the open `era` to the sum of its `EraVolumes` and its
+        /// excluded self-trades, correcting the drift of manual adjustments; each call sums at
+        /// most `count` participants and the total is replaced by the call reaching the last
+        #[pallet::weight(10000000)]
+        pub fn reconcile_era(
+            origin: OriginFor<T>,
+            era: Era<T>,
+            count: u32,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let era = Self::era_of(era).start();
+            ensure!(
+                !Self::is_era_closed(era, frame_system::Pallet::<T>::block_number()),
+                Error::<T>::EraAlreadyClosed
+            );
+            let count = count.min(T::MaxParticipants::get()).max(1);
+            let (mut entries, mut sum) = match ReconcileProgress::<T>::get(era) {
+                Some((last, sum)) => (EraVolumes::<T>::iter_prefix_from(era, last), sum),
+                None => (EraVolumes::<T>::iter_prefix(era), Zero::zero()),
+            };
+            let mut last = None;
+            for (who, vol) in entries.by_ref().take(count as usize) {
+                sum = sum.checked_add(&vol).ok_or(Error::<T>::Overflow)?;
+                last = Some(who);
+            }
+            if let (Some(who), Some(_)) = (last, entries.next()) {
+                let last = EraVolumes::<T>::hashed_key_for(era, &who);
+                ReconcileProgress::<T>::insert(era, (last, sum));
+                return Ok(().into());
+            }
+            ReconcileProgress::<T>::remove(era);
+            let new = sum
+                .checked_add(&Self::self_trade_volumes(era))
+                .ok_or(Error::<T>::Overflow)?;
+            let old = Volumes::<T>::mutate(Self::era_of(era), |v| sp_std::mem::replace(v, new));
+            Self::deposit_event(Event::EraReconciled(era, old, new));
+            Ok(().into())
+        }
+
+        /// record the total volume and the participants of the closed `era` for disputes
+        #[pallet::weight(10000000)]
+        pub fn snapshot_era(origin: OriginFor<T>, era: Era<T>) -> DispatchResultWithPostInfo {
+            T::KeeperOrigin::ensure_origin(origin)?;
+            let era = Self::era_of(era).start();
+            ensure!(
+                Self::is_era_closed(era, frame_system::Pallet::<T>::block_number()),
+                Error::<T>::EraNotClosed
+            );
+            ensure!(
+                !EraSnapshots::<T>::contains_key(era),
+                Error::<T>::AlreadySnapshotted
+            );
+            let count = Self::participants(era);
+            ensure!(
+                count <= T::MaxParticipants::get(),
+                Error::<T>::TooManyParticipants
+            );
+            let total_vol = Self::volumes(Self::era_of(era));
+            let participants = EraVolumes::<T>::iter_prefix(era).collect();
+            EraSnapshots::<T>::insert(
+                era,
+                EraSnapshot {
+                    total_vol,
+                    participants,
+                },
+            );
+            Self::deposit_event(Event::EraSnapshotted(era, total_vol, count));
+            Ok(().into())
+        }
+
+        #[pallet::weight(10000000)]
+        pub fn set_kyc_hold(origin: OriginFor<T>, who: T::AccountId) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            KycHold::<T>::insert(&who, true);
+            Self::deposit_event(Event::KycHoldSet(who));
+            Ok(().into())
+        }
+
+        #[pallet::weight(10000000)]
+        pub fn clear_kyc_hold(
+            origin: OriginFor<T>,
+            who: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            KycHold::<T>::remove(&who);
+            Self::deposit_event(Event::KycHoldCleared(who));
+            Ok(().into())
+        }
+
+        #[pallet::weight(10000000)]
+        pub fn slash_volume(
+            origin: OriginFor<T>,
+            who: T::AccountId,
+            vol: Volume<T>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let at = frame_system::Pallet::<T>::block_number();
+            Self::remove_volume(&who, vol, at)?;
+            Ok(().into())
+        }
+
+        #[pallet::weight(10000000)]
+        pub fn take_pool_reward(
+            origin: OriginFor<T>,
+            pool_id: T::PoolId,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(!Self::kyc_hold(&who), Error::<T>::KycRequired);
+            let at = frame_system::Pallet::<T>::block_number();
+            let reward = Self::claim_pool_reward(pool_id, &who, at)?;
+            Self::deposit_event(Event::PoolRewardClaimed(pool_id, who, reward));
+            Ok(().into())
+        }
+
+        #[pallet::weight(10000000)]
+        pub fn set_pool_rewards_per_era(
+            origin: OriginFor<T>,
+            pool_id: T::PoolId,
+            rewards_per_era: Balance<T>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            PoolRewardsPerEra::<T>::insert(pool_id, rewards_per_era);
+            Self::deposit_event(Event::PoolRewardsPerEraSet(pool_id, rewards_per_era));
+            Ok(().into())
+        }
+
+        /// import the pending volumes of the accounts migrated from another chain, each of
+        /// them is added to the total of its era as if it was traded there, all along the era
+        /// under `ActiveBlockWeighting`
+        #[pallet::weight(10000000)]
+        #[transactional]
+        pub fn import_volume_snapshot(
+            origin: OriginFor<T>,
+            entries: BoundedVec<(T::AccountId, Volume<T>, Era<T>), T::MaxImport>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+            let count = entries.len() as u32;
+            for (who, vol, era) in entries.into_iter() {
+                ensure!(Self::era_of(era).start() == era, Error::<T>::MisalignedEra);
+                ensure!(!Self::is_finalized(era), Error::<T>::EraFinalized);
+                ensure!(
+                    !Rewards::<T>::contains_key(&who),
+                    Error::<T>::DuplicateEntry
+                );
+                Volumes::<T>::try_mutate(Self::era_of(era), |v| -> DispatchResult {
+                    Ok(*v = v.checked_add(&vol).ok_or(Error::<T>::Overflow)?)
+                })?;
+                EraVolumes::<T>::insert(&era, &who, vol);
+                Participants::<T>::mutate(&era, |n| *n = n.saturating_add(1));
+                if T::ActiveBlockWeighting::get() {
+                    Self::extend_span(era, &who, Self::era_span(era));
+                }
+                Self::adjust_pending(Zero::zero(), vol);
+                Rewards::<T>::insert(
+                    &who,
+                    Reward {
+                        confirmed: Zero::zero(),
+                        pending_vol: vol,
+                        last_modify: era,
+                    },
+                );
+            }
+            Self::deposit_event(Event::VolumeSnapshotImported(count));
+            Ok(().into())
+        }
+
+        /// move `amount` of the signer into the pool account earmarked for the future emissions
+        #[pallet::weight(10000000)]
+        #[transactional]
+        pub fn fund_pool(origin: OriginFor<T>, amount: Balance<T>) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let native = T::Asset::native_token_id();
+            T::Asset::try_mutate_account(&native, &who, |b| -> DispatchResult {
+                b.0 =
+                    b.0.checked_sub(&amount)
+                        .ok_or(Error::<T>::InsufficientBalance)?;
+                Ok(())
+            })?;
+            T::Asset::try_mutate_account(&native, &Self::pool_account(), |b| -> DispatchResult {
+                b.0 = b.0.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+                Ok(())
+            })?;
+            PoolBalance::<T>::try_mutate(|p| -> DispatchResult {
+                Ok(*p = p.checked_add(&amount).ok_or(Error::<T>::Overflow)?)
+            })?;
+            Self::deposit_event(Event::PoolFunded(who, amount));
+            Ok(().into())
+        }
+
+        /// move `amount` of the signer into the pool account for the pool of the current or a
+        /// future era
+        #[pallet::weight(10000000)]
+        #[transactional]
+        pub fn donate_to_era(
+            origin: OriginFor<T>,
+            era: Era<T>,
+            amount: Balance<T>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let era = Self::era_of(era).start();
+            let now = frame_system::Pallet::<T>::block_number();
+            ensure!(!Self::is_era_closed(era, now), Error::<T>::EraAlreadyClosed);
+            let native = T::Asset::native_token_id();
+            T::Asset::try_mutate_account(&native, &who, |b| -> DispatchResult {
+                b.0 =
+                    b.0.checked_sub(&amount)
+                        .ok_or(Error::<T>::InsufficientBalance)?;
+                Ok(())
+            })?;
+            T::Asset::try_mutate_account(&native, &Self::pool_account(), |b| -> DispatchResult {
+                b.0 = b.0.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+                Ok(())
+            })?;
+            PoolBalance::<T>::try_mutate(|p| -> DispatchResult {
+                Ok(*p = p.checked_add(&amount).ok_or(Error::<T>::Overflow)?)
+            })?;
+            EraBonusPool::<T>::mutate(&era, |p| *p = p.saturating_add(amount));
+            Self::deposit_event(Event::EraDonated(who, era, amount));
+            Ok(().into())
+        }
+
+        /// reserve a deposit to keep the account alive so that a first claim below the
+        /// existential deposit isn't dusted, the deposit is refunded by the first claim
+        #[pallet::weight(10000000)]
+        pub fn register_for_rewards(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                !Registrations::<T>::contains_key(&who),
+                Error::<T>::AlreadyRegistered
+            );
+            let deposit = T::RegistrationDeposit::get();
+            T::Asset::try_mutate_account(
+                &T::Asset::native_token_id(),
+                &who,
+                |b| -> DispatchResult {
+                    b.0 =
+                        b.0.checked_sub(&deposit)
+                            .ok_or(Error::<T>::InsufficientBalance)?;
+                    b.1 = b.1.checked_add(&deposit).ok_or(Error::<T>::Overflow)?;
+                    Ok(())
+                },
+            )?;
+            Registrations::<T>::insert(&who, deposit);
+            Self::deposit_event(Event::Registered(who, deposit));
+            Ok(().into())
+        }
+
+        #[pallet::weight(10000000)]
+        pub fn bind_referrer(
+            origin: OriginFor<T>,
+            referrer: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(who != referrer, Error::<T>::SelfReferral);
+            ensure!(
+                !Referrer::<T>::contains_key(&who),
+                Error::<T>::ReferrerAlreadyBound
+            );
+            Referrer::<T>::insert(&who, &referrer);
+            Self::deposit_event(Event::ReferrerBound(who, referrer));
+            Ok(().into())
+        }
+
+        /// emit the retained `EraRewards` of `who` for the eras from `from_era` to `to_era`
+        /// inclusively, for clients replaying the claims without RPC access
+        #[pallet::weight(10000000)]
+        pub fn emit_claim_history(
+            origin: OriginFor<T>,
+            who: T::AccountId,
+            from_era: Era<T>,
+            to_era: Era<T>,
+        ) -> DispatchResultWithPostInfo {
+            let _ = ensure_signed(origin)?;
+            for era in Self::retained_eras(from_era, to_era)? {
+                Self::deposit_event(Event::EraRewardEntry(
+                    who.clone(),
+                    era,
+                    Self::era_rewards(era, &who),
+                ));
+            }
+            Ok(().into())
+        }
+
+        /// emit the reward state of `who` without mutating it
+        #[pallet::weight(10000000)]
+        pub fn inspect_reward(
+            origin: OriginFor<T>,
+            who: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let _ = ensure_signed(origin)?;
+            let reward = Self::rewards(&who);
+            let projected = Self::projected_reward(&who);
+            Self::deposit_event(Event::RewardInspected(
+                who,
+                reward.confirmed,
+                reward.pending_vol,
+                reward.last_modify,
+                projected,
+            ));
+            Ok(().into())
+        }
+    }
+
+    impl<T: Config> Pallet<T>
+    where
+        Balance<T>: From<u128>,
+    {
+        /// the total volumes of the retained eras from `from_era` to `to_era` inclusively
+        pub fn volume_history(
+            from_era: Era<T>,
+            to_era: Era<T>,
+        ) -> Result<Vec<(Era<T>, Volume<T>)>, DispatchError> {
+            Ok(Self::retained_eras(from_era, to_era)?
+                .into_iter()
+                .map(|era| (era, Self::volumes(Self::era_of(era))))
+                .collect())
+        }
+
+        /// the rewards confirmed to `who` for the retained eras strictly after `since_era`
+        pub fn reward_delta_since(who: &T::AccountId, since_era: Era<T>) -> Balance<T> {
+            let now = Self::era_of(frame_system::Pallet::<T>::block_number()).start();
+            let retained = Self::era_duration().saturating_mul(T::HistoryDepth::get().into());
+            let mut era =
+                Self::next_era(since_era).max(Self::era_of(now.saturating_sub(retained)).start());
+            let mut delta: Balance<T> = Zero::zero();
+            while era < now {
+                delta = delta.saturating_add(Self::era_rewards(era, who));
+                era = Self::next_era(era);
+            }
+            delta
+        }
+
+        /// the eras from `from_era` to `to_era` inclusively, which must be within the
+        /// `HistoryDepth` eras before the current one and at most `MaxHistoryRange` long
+        fn retained_eras(from_era: Era<T>, to_era: Era<T>) -> Result<Vec<Era<T>>, DispatchError> {
+            let from_era = Self::era_of(from_era).start();
+            let to_era = Self::era_of(to_era).start();
+            let now = Self::era_of(frame_system::Pallet::<T>::block_number()).start();
+            let retained = Self::era_duration().saturating_mul(T::HistoryDepth::get().into());
+            ensure!(
+                from_era <= to_era && from_era >= now.saturating_sub(retained),
+                Error::<T>::EraNotRetained
+            );
+            let mut range = Vec::new();
+            let mut era = from_era;
+            while era <= to_era {
+                ensure!(
+                    (range.len() as u32) < T::MaxHistoryRange::get(),
+                    Error::<T>::RangeTooLong
+                );
+                range.push(era);
+                era = Self::next_era(era);
+            }
+            Ok(range)
+        }
+
+        /// the pool of `era` left to the traders after the cut of the operator
+        pub fn traders_pool(era: Era<T>) -> Balance<T> {
+            let pool: u128 = Self::era_reward_at(era).unique_saturated_into();
+            pool.saturating_sub(Self::operator_share_of(era).mul_floor(pool))
+                .into()
+        }
+
+        /// the monotonic index of the era containing `at`, counting the eras of every duration
+        /// before it
+        pub fn current_era_index(at: T::BlockNumber) -> u64 {
+            let mut index = 0u64;
+            let (mut from, mut era_duration) = (Zero::zero(), T::EraDuration::get());
+            for (next_from, next_duration) in Self::era_alignments() {
+                if next_from > at {
+                    break;
+                }
+                let eras: u64 = ((next_from - from) / era_duration).unique_saturated_into();
+                index = index.saturating_add(eras);
+                from = next_from;
+                era_duration = next_duration;
+            }
+            let eras: u64 = ((at - from) / era_duration).unique_saturated_into();
+            index.saturating_add(eras)
+        }
+
+        /// a page of the `Rewards` of all accounts after the raw key `cursor` for an off-chain
+        /// backup, along with the cursor of the next page unless this is the last one, so the
+        /// entries written in between don't shift the pages
+        pub fn export_state(
+            cursor: Option<Vec<u8>>,
+        ) -> (
+            Vec<(T::AccountId, Reward<Balance<T>, Volume<T>, Era<T>>)>,
+            Option<Vec<u8>>,
+        ) {
+            let mut entries = match cursor {
+                Some(last) => Rewards::<T>::iter_from(last),
+                None => Rewards::<T>::iter(),
+            };
+            let exported: Vec<_> = entries
+                .by_ref()
+                .take(T::MaxExportPage::get() as usize)
+                .collect();
+            let next = match (exported.last(), entries.next()) {
+                (Some((who, _)), Some(_)) => Some(Rewards::<T>::hashed_key_for(who)),
+                _ => None,
+            };
+            (exported, next)
+        }
+
+        /// a page of the retained `EraVolumes` after the raw key `cursor`, the `Volumes` of the
+        /// eras are their sums
+        pub fn export_volumes(
+            cursor: Option<Vec<u8>>,
+        ) -> (Vec<(Era<T>, T::AccountId, Volume<T>)>, Option<Vec<u8>>) {
+            let mut entries = match cursor {
+                Some(last) => EraVolumes::<T>::iter_from(last),
+                None => EraVolumes::<T>::iter(),
+            };
+            let exported: Vec<_> = entries
+                .by_ref()
+                .take(T::MaxExportPage::get() as usize)
+                .collect();
+            let next = match (exported.last(), entries.next()) {
+                (Some((era, who, _)), Some(_)) => Some(EraVolumes::<T>::hashed_key_for(era, who)),
+                _ => None,
+            };
+            (exported, next)
+        }
+
+        /// the distribution of a closed era computed from the retained `EraVolumes` without
+        /// mutating the state, paginated from `start` by at most `MaxParticipants` entries.
+        /// The caps of the era pool and the total emission are not applied.
+        pub fn simulate_era(
+            era: Era<T>,
+            start: u32,
+            count: u32,
+        ) -> Vec<(T::AccountId, Balance<T>)> {
+            let era = Self::era_of(era).start();
+            let total_vol = T::VolumeToU128::convert(Self::volumes(Self::era_of(era)));
+            if !Self::is_era_closed(era, frame_system::Pallet::<T>::block_number())
+                || total_vol == 0
+            {
+                return Vec::new();
+            }
+            let era_reward: u128 = Self::traders_pool(era).unique_saturated_into();
+            let count = count.min(T::MaxParticipants::get()) as usize;
+            Self::era_allocation(era, total_vol, era_reward, start as usize, count)
+                .into_iter()
+                .map(|(who, share)| (who, share.into()))
+                .collect()
+        }
+
+        /// record the trading of a category pool, which accrues independently of the others
+        /// and of the default pool fed by `Rewarding::save_trading`, with the same staging of
+        /// the eras not closed yet and the same finalization
+        #[transactional]
+        pub fn save_pool_trading(
+            pool_id: T::PoolId,
+            trader: &T::AccountId,
+            vol: Volume<T>,
+            at: T::BlockNumber,
+        ) -> DispatchResult {
+            if vol == Zero::zero() {
+                return Ok(());
+            }
+            ensure!(
+                !T::StrictTimestamps::get() || at <= frame_system::Pallet::<T>::block_number(),
+                Error::<T>::FutureEra
+            );
+            let block = at;
+            let at = Self::era_of(at).start();
+            ensure!(
+                !Self::is_pool_finalized(pool_id, at),
+                Error::<T>::EraFinalized
+            );
+            Self::note_trade(trader, vol, block)?;
+            PoolVolumes::<T>::try_mutate(pool_id, &at, |v| -> DispatchResult {
+                Ok(*v = v.checked_add(&vol).ok_or(Error::<T>::Overflow)?)
+            })?;
+            KnownPools::<T>::insert(pool_id, true);
+            Self::rotate_pool_reward(pool_id, at, vol, trader)?;
+            Ok(())
+        }
+
+        /// record the trading of `token` into the pool of the token. The totals per token are
+        /// the `PoolVolumes` of the token pools rather than `Volumes` keyed by the token, as
+        /// `Rewarding::save_trading` carries no token and keeps feeding the default pool; the
+        /// token pools are staged, finalized and pruned like any other pool
+        pub fn save_token_trading(
+            token: TokenId<T>,
+            trader: &T::AccountId,
+            vol: Volume<T>,
+            at: T::BlockNumber,
+        ) -> DispatchResult {
+            Self::save_pool_trading(T::TokenPool::convert(token), trader, vol, at)
+        }
+
+        /// `Rewarding::save_trading` attributing the volume to `pair_id` if any, so a finalized
+        /// era is rejected before anything is attributed. The `PairVolumes` only report the
+        /// markets, the rewards are still split by the era pool, and are pruned with the era
+        pub fn save_pair_trading(
+            pair_id: Option<T::PairId>,
+            trader: &T::AccountId,
+            vol: Volume<T>,
+            at: T::BlockNumber,
+        ) -> DispatchResult {
+            <Self as Rewarding<T::AccountId, Volume<T>, T::BlockNumber>>::save_trading(
+                trader, vol, at,
+            )?;
+            if let Some(pair_id) = pair_id {
+                let era = Self::era_of(at).start();
+                PairVolumes::<T>::mutate(pair_id, era, |v| *v = v.saturating_add(vol));
+                KnownPairs::<T>::insert(pair_id, true);
+            }
+            Ok(())
+        }
+
+        /// the volume traded on `pair_id` during the era containing `at`
+        pub fn pair_volume(pair_id: T::PairId, at: T::BlockNumber) -> Volume<T> {
+            Self::pair_volumes(pair_id, Self::era_of(at).start())
+        }
+
+        /// the volume traded in `token` during the era containing `at`
+        pub fn token_volumes(token: TokenId<T>, at: T::BlockNumber) -> Volume<T> {
+            let at = Self::era_of(at).start();
+            Self::pool_volumes(T::TokenPool::convert(token), at)
+        }
+
+        /// the fraction of the confirmed rewards which have been claimed
+        pub fn claim_rate() -> Perquintill {
+            let distributed: u128 = Self::total_distributed().unique_saturated_into();
+            if distributed == 0 {
+                return Perquintill::zero();
+            }
+            let claimed: u128 = Self::total_claimed().unique_saturated_into();
+            Perquintill::from_rational(claimed, distributed)
+        }
+
+        /// the epoch grouping `ErasPerEpoch` consecutive eras for reporting
+        pub fn epoch_of(era: Era<T>) -> u64 {
+            Self::current_era_index(era) / u64::from(T::ErasPerEpoch::get().max(1))
+        }
+
+        /// the part of the era pool not confirmed to anyone yet. Rewards are confirmed lazily
+        /// after the era closes, so an open era always returns the full pool while a closed era
+        /// returns the pool minus what the settled participants have confirmed so far.
+        pub fn remaining_pool(era: Era<T>) -> Balance<T> {
+            let era = Self::era_of(era).start();
+            Self::traders_pool(era).saturating_sub(Self::era_distributed(era))
+        }
+
+        /// the rewards `who` could claim now under the `AccrualModel`
+        pub fn total_claimable(who: &T::AccountId) -> Balance<T> {
+            T::AccrualModel::claimable(who)
+        }
+
+        /// the rank of `who` by its volume in `era` and the participants of the era, for the
+        /// runtime API only as it iterates the `EraVolumes` of the era in O(participants).
+        /// The rank is 0 if `who` didn't trade or the era has above `MaxParticipants`.
+        pub fn era_rank(who: &T::AccountId, era: Era<T>) -> (u32, u32) {
+            let era = Self::era_of(era).start();
+            let participants = Self::participants(era);
+            let vol = Self::era_volumes(era, who);
+            if vol.is_zero() || participants > T::MaxParticipants::get() {
+                return (0, participants);
+            }
+            let above = EraVolumes::<T>::iter_prefix_values(era)
+                .filter(|v| *v > vol)
+                .count() as u32;
+            (above + 1, participants)
+        }
+
+        /// the reward `who` would have earned in `era` with all of its volume, i.e. the pool
+        /// of the traders, to compare the actual `EraRewards` against
+        pub fn max_possible_reward(_who: &T::AccountId, era: Era<T>) -> Balance<T> {
+            Self::traders_pool(Self::era_of(era).start())
+        }
+
+        /// the `total_claimable` of each account, only the first `MaxBatchQuery` are answered
+        pub fn pending_rewards_batch(accounts: Vec<T::AccountId>) -> Vec<Balance<T>> {
+            accounts
+                .iter()
+                .take(T::MaxBatchQuery::get() as usize)
+                .map(Self::total_claimable)
+                .collect()
+        }
+
+        /// whether the confirmations of the closed `era` are consistent, for the runtime API
+        /// only as it iterates the `EraRewards` of the era: none exceeds the share its account
+        /// is allocated by the settlement of the era and all of them sum to `EraDistributed`,
+        /// both within one unit per participant. An open era or one whose `EraRewards` were
+        /// pruned can't be verified.
+        pub fn verify_era(era: Era<T>) -> bool {
+            let era = Self::era_of(era).start();
+            let now = frame_system::Pallet::<T>::block_number();
+            let retained = Self::era_duration().saturating_mul(T::HistoryDepth::get().into());
+            if !Self::is_era_closed(era, now)
+                || era < Self::era_of(now).start().saturating_sub(retained)
+            {
+                return false;
+            }
+            let total_vol = T::VolumeToU128::convert(Volumes::<T>::get(EraId(era)));
+            let pool: u128 = Self::traders_pool(era).unique_saturated_into();
+            let allocated: BTreeMap<T::AccountId, u128> = if total_vol == 0 {
+                BTreeMap::new()
+            } else {
+                Self::era_allocation(era, total_vol, pool, 0, usize::MAX)
+                    .into_iter()
+                    .collect()
+            };
+            let mut recorded = 0u128;
+            for (who, a) in EraRewards::<T>::iter_prefix(era) {
+                if total_vol == 0 {
+                    return false;
+                }
+                let a: u128 = a.unique_saturated_into();
+                let share = allocated.get(&who).copied().unwrap_or_default();
+                if a > share.saturating_add(1) {
+                    return false;
+                }
+                recorded = recorded.saturating_add(a);
+            }
+            let distributed: u128 = Self::era_distributed(era).unique_saturated_into();
+            let tolerance = u128::from(Self::participants(era));
+            recorded.max(distributed) - recorded.min(distributed) <= tolerance
+        }
+
+        /// the state of the rewards of `who` derived from its `Rewards` entry
+        pub fn account_status(who: &T::AccountId) -> RewardStatus {
+            let reward = Self::rewards(who);
+            if !reward.confirmed.is_zero() {
+                RewardStatus::ConfirmedUnclaimed
+            } else if !reward.pending_vol.is_zero() {
+                RewardStatus::PendingOnly
+            } else if Rewards::<T>::contains_key(who) || LastTradeBlock::<T>::contains_key(who) {
+                RewardStatus::SettledZero
+            } else {
+                RewardStatus::NeverTraded
+            }
+        }
+
+        /// the confirmed rewards, the `projected_reward` and the pending volume of `who` along
+        /// with the current era, everything a dashboard needs in one query
+        pub fn reward_breakdown(who: &T::AccountId) -> (Balance<T>, Balance<T>, Volume<T>, Era<T>) {
+            let reward = Self::rewards(who);
+            let now = Self::era_of(frame_system::Pallet::<T>::block_number()).start();
+            (
+                reward.confirmed,
+                Self::projected_reward(who),
+                reward.pending_vol,
+                now,
+            )
+        }
+
+        /// the reward of the pending volume if its era closed with the current total volume
+        pub fn projected_reward(who: &T::AccountId) -> Balance<T> {
+            let reward = Self::rewards(who);
+            let total_vol = T::VolumeToU128::convert(Self::volumes(EraId(reward.last_modify)));
+            if reward.pending_vol.is_zero() || total_vol == 0 {
+                return Zero::zero();
+            }
+            let pending_vol = T::VolumeToU128::convert(reward.pending_vol);
+            let denominator = total_vol.max(T::MinDenominator::get());
+            let p: Perquintill = Perquintill::from_rational(pending_vol, denominator);
+            let era_reward: u128 = Self::traders_pool(reward.last_modify).unique_saturated_into();
+            p.mul_floor(era_reward).into()
+        }
+
+        /// the reward of the volumes of `who` staged in the eras closed since, confirmed by its
+        /// next settlement
+        pub fn staged_reward(who: &T::AccountId) -> Balance<T> {
+            StagedVolumes::<T>::iter_prefix(who)
+                .filter(|(era, _)| Self::is_final(*era))
+                .fold(Zero::zero(), |acc: Balance<T>, (era, vol)| {
+                    let total_vol = T::VolumeToU128::convert(Self::volumes(EraId(era)));
+                    let denominator = total_vol.max(T::MinDenominator::get());
+                    let p = Perquintill::from_rational(T::VolumeToU128::convert(vol), denominator);
+                    let era_reward: u128 = Self::traders_pool(era).unique_saturated_into();
+                    acc.saturating_add(p.mul_floor(era_reward).into())
+                })
+        }
+
+        /// the emission of the era following the one containing `at`, limited by what is left
+        /// of `MaxTotalEmission`
+        pub fn next_era_emission(at: T::BlockNumber) -> Balance<T> {
+            let next_era = Self::next_era(at);
+            let headroom = T::MaxTotalEmission::get().saturating_sub(Self::total_distributed());
+            Self::era_reward_at(next_era).min(headroom)
+        }
+
+        /// the reward each further unit of volume traded in `era` would earn with its current
+        /// total, zero once the era is closed at `at`, and the whole pool before any volume
+        pub fn marginal_reward_per_volume(era: Era<T>, at: T::BlockNumber) -> Balance<T> {
+            let era = Self::era_of(era).start();
+            if Self::is_era_closed(era, at) {
+                return Zero::zero();
+            }
+            let total_vol = T::VolumeToU128::convert(Self::volumes(EraId(era)));
+            let denominator = total_vol.max(T::MinDenominator::get());
+            let era_reward: u128 = Self::traders_pool(era).unique_saturated_into();
+            if denominator == 0 {
+                return era_reward.into();
+            }
+            (era_reward / denominator).into()
+        }
+
+        /// the eras from `from_era` to `to_era` inclusively with a donation or a scheduled
+        /// bonus on top of the regular emission, along with the bonus, the range is cut at
+        /// `MaxHistoryRange` eras
+        pub fn boosted_eras(from_era: Era<T>, to_era: Era<T>) -> Vec<(Era<T>, Balance<T>)> {
+            let mut era = Self::era_of(from_era).start();
+            let to_era = Self::era_of(to_era).start();
+            let mut boosted = Vec::new();
+            for _ in 0..T::MaxHistoryRange::get() {
+                if era > to_era {
+                    break;
+                }
+                let bonus = Self::era_bonus_pool(era).saturating_add(Self::scheduled_bonus(era));
+                if !bonus.is_zero() {
+                    boosted.push((era, bonus));
+                }
+                era = Self::next_era(era);
+            }
+            boosted
+        }
+
+        /// the share of the volume of `era` the reward of `who` was confirmed from, if kept by
+        /// `RecordShares` and computed from a share, i.e. not by `ExactDistribution` or
+        /// `ActiveBlockWeighting`
+        pub fn era_share_precise(who: &T::AccountId, era: Era<T>) -> Option<Perquintill> {
+            Self::precise_shares(Self::era_of(era).start(), who)
+        }
+    }
+
+    impl<T: Config> Pallet<T>
+    where
+        Balance<T>: From<u128>,
+    {
+        fn ensure_claimable(who: &T::AccountId, at: T::BlockNumber) -> DispatchResult {
+            ensure!(!Self::kyc_hold(who), Error::<T>::KycRequired);
+            let claimable = Self::total_claimable(who);
+            ensure!(
+                claimable >= T::MinClaimable::get(),
+                Error::<T>::BelowMinClaim
+            );
+            // large rewards needn't wait for the cooldown
+            if claimable <= T::LargeClaimThreshold::get() {
+                if let Some(last_claim) = Self::last_claim(who) {
+                    ensure!(
+                        at >= last_claim.saturating_add(T::MinClaimInterval::get()),
+                        Error::<T>::ClaimTooFrequent
+                    );
+                }
+            }
+            Ok(())
+        }
+
+        /// a claim never depends on a total that is still mutable, so it can't be front-run:
+        /// `at` is the current era, whose own pending volume is left unsettled, while an older
+        /// pending era is closed and gets finalized by its first settlement, rejecting any
+        /// volume reported for it afterwards. Hence a trade and a claim within the same block
+        /// pay nothing for that trade: its volume is pending in the open era until it closes.
+        ///
+        /// `confirmed` is zeroed in the same transaction as the credit, so re-executing a claim
+        /// on the claimed state, e.g. on another fork, credits nothing again.
+        #[transactional]
+        fn claim_reward(
+            who: &T::AccountId,
+            at: T::BlockNumber,
+            as_reserved: bool,
+        ) -> Result<Balance<T>, DispatchError> {
+            Self::claim_reward_capped(who, at, as_reserved, Bounded::max_value())
+        }
+
+        /// `claim_reward` paying at most `max` of the confirmed rewards, the rest stays
+        /// confirmed
+        #[transactional]
+        fn claim_reward_capped(
+            who: &T::AccountId,
+            at: T::BlockNumber,
+            as_reserved: bool,
+            max: Balance<T>,
+        ) -> Result<Balance<T>, DispatchError> {
+            let confirmed = T::AccrualModel::settle(who, Self::era_of(at))?;
+            if confirmed == Zero::zero() {
+                return Ok(Zero::zero());
+            }
+            let confirmed = T::AccrualModel::take(who, max)?;
+            if confirmed.is_zero() {
+                return Ok(confirmed);
+            }
+            // the burned cut is never credited but still leaves the confirmed rewards
+            let raw: u128 = confirmed.unique_saturated_into();
+            let burned: Balance<T> = T::ClaimBurn::get().mul_floor(raw).into();
+            let credited = confirmed - burned;
+            let deposit = Registrations::<T>::take(&who).unwrap_or_default();
+            // a reserved claim vests all the same, only its instant part is reserved
+            let instant = Self::instant_part(credited);
+            let vesting = credited - instant;
+            T::Asset::try_mutate_account(&T::Asset::native_token_id(), &who, |b| {
+                // refund the registration deposit along with the first claim
+                let refund = deposit.min(b.1);
+                b.1 -= refund;
+                b.0 += refund;
+                if as_reserved {
+                    b.1 += instant;
+                } else {
+                    b.0 += instant;
+                }
+                Ok(b.1 += vesting)
+            })?;
+            if !vesting.is_zero() {
+                Self::schedule_vesting(who, vesting, at)?;
+            }
+            TotalClaimed::<T>::mutate(|c| *c = c.saturating_add(confirmed));
+            if !burned.is_zero() {
+                Self::deposit_event(Event::RewardBurned(who.clone(), burned));
+            }
+            Ok(credited)
+        }
+
+        /// remove at most `max` of the confirmed rewards of `who`, dropping the entry left
+        /// with nothing
+        fn take_confirmed(who: &T::AccountId, max: Balance<T>) -> Balance<T> {
+            Rewards::<T>::mutate_exists(who, |r| {
+                let mut reward: Reward<Balance<T>, Volume<T>, Era<T>> =
+                    r.take().unwrap_or_default();
+                let confirmed = reward.confirmed.min(max);
+                reward.confirmed = reward.confirmed - confirmed;
+                if reward.pending_vol > Zero::zero() || reward.confirmed > Zero::zero() {
+                    r.replace(reward);
+                }
+                confirmed
+            })
+        }
+
+        /// the part of the claimed `credited` not vesting
+        fn instant_part(credited: Balance<T>) -> Balance<T> {
+            let raw: u128 = credited.unique_saturated_into();
+            T::InstantShare::get().mul_floor(raw).into()
+        }
+
+        /// release what has vested so far and vest the rest together with `amount` from `at`
+        /// until the ends of both weighted by their amounts, so neither is vested from scratch
+        fn schedule_vesting(
+            who: &T::AccountId,
+            amount: Balance<T>,
+            at: T::BlockNumber,
+        ) -> DispatchResult {
+            Self::release_vested(who, at)?;
+            let period = T::VestingPeriod::get();
+            let (left, end) = Self::vesting(who)
+                .map(|v| (v.total.saturating_sub(v.released), v.end.max(at)))
+                .unwrap_or_else(|| (Zero::zero(), at));
+            let total = left.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+            let (left, amount, total_raw): (u128, u128, u128) = (
+                left.unique_saturated_into(),
+                amount.unique_saturated_into(),
+                total.unique_saturated_into(),
+            );
+            let left_blocks: u128 = (end - at).unique_saturated_into();
+            let new_blocks: u128 = period.unique_saturated_into();
+            let weighted = (U256::from(left) * U256::from(left_blocks)
+                + U256::from(amount) * U256::from(new_blocks))
+                / U256::from(total_raw.max(1));
+            let blocks: T::BlockNumber = weighted.low_u128().unique_saturated_into();
+            Vesting::<T>::insert(
+                who,
+                VestingInfo {
+                    total,
+                    released: Zero::zero(),
+                    start: at,
+                    end: at.saturating_add(blocks),
+                },
+            );
+            Ok(())
+        }
+
+        /// unreserve the part of the vesting rewards of `who` vested at `now`
+        fn release_vested(
+            who: &T::AccountId,
+            now: T::BlockNumber,
+        ) -> Result<Balance<T>, DispatchError> {
+            let mut info = match Self::vesting(who) {
+                Some(info) => info,
+                None => return Ok(Zero::zero()),
+            };
+            let elapsed: u128 = now.saturating_sub(info.start).unique_saturated_into();
+            let period: u128 = info.end.saturating_sub(info.start).unique_saturated_into();
+            let total: u128 = info.total.unique_saturated_into();
+            let vested: Balance<T> = if elapsed >= period {
+                info.total
+            } else {
+                Perquintill::from_rational(elapsed, period)
+                    .mul_floor(total)
+                    .into()
+            };
+            let amount = vested.saturating_sub(info.released);
+            let amount = T::Asset::try_mutate_account(&T::Asset::native_token_id(), who, |b| {
+                let amount = amount.min(b.1);
+                b.1 -= amount;
+                b.0 += amount;
+                Ok(amount)
+            })?;
+            info.released = info.released.saturating_add(amount);
+            if info.released >= info.total {
+                Vesting::<T>::remove(who);
+            } else {
+                Vesting::<T>::insert(who, info);
+            }
+            Ok(amount)
+        }
+
+        #[transactional]
+        fn credit_operator(era: Era<T>) -> DispatchResult {
+            Self::freeze_params(era);
+            let pool: u128 = Self::era_reward_at(era).unique_saturated_into();
+            let cut = Self::emit(Self::operator_share_of(era).mul_floor(pool).into())?;
+            OperatorPaid::<T>::insert(era, true);
+            if cut.is_zero() {
+                return Ok(());
+            }
+            let operator = T::OperatorAccount::get();
+            Rewards::<T>::try_mutate(&operator, |r| -> DispatchResult {
+                r.confirmed = r.confirmed.checked_add(&cut).ok_or(Error::<T>::Overflow)?;
+                Ok(())
+            })?;
+            Self::deposit_event(Event::OperatorCredited(operator, era, cut));
+            Ok(())
+        }
+
+        /// the volume traded at the block `at` plus the largest boost of the windows covering it
+        /// `vol` with the `EarlyBirdMultiplier` if it is the first trade of `trader` in `era`,
+        /// i.e. it finds no volume of the trader yet, neither recorded nor buffered
+        fn early_bird(
+            trader: &T::AccountId,
+            vol: Volume<T>,
+            era: Era<T>,
+        ) -> Result<Volume<T>, DispatchError> {
+            let buffered = T::BatchSettlement::get()
+                && Self::trade_buffer()
+                    .iter()
+                    .any(|(who, _, at, excluded)| who == trader && *at == era && !excluded);
+            if buffered || !Self::era_volumes(era, trader).is_zero() {
+                return Ok(vol);
+            }
+            let raw: u128 = T::VolumeToU128::convert(vol);
+            let boost = T::U128ToVolume::convert(T::EarlyBirdMultiplier::get().mul_floor(raw));
+            Ok(vol.checked_add(&boost).ok_or(Error::<T>::Overflow)?)
+        }
+
+        fn boosted(vol: Volume<T>, at: T::BlockNumber) -> Result<Volume<T>, DispatchError> {
+            let extra = Self::boost_windows()
+                .into_iter()
+                .filter(|(start, end, _)| *start <= at && at <= *end)
+                .map(|(_, _, extra)| extra)
+                .max()
+                .unwrap_or_else(Perquintill::zero);
+            let raw: u128 = T::VolumeToU128::convert(vol);
+            let boost = T::U128ToVolume::convert(extra.mul_floor(raw));
+            let vol = vol.checked_add(&boost).ok_or(Error::<T>::Overflow)?;
+            Ok(vol)
+        }
+
+        /// the checks and the adjustments every trade goes through before it is recorded: the
+        /// timestamp, the finality of the era, the block cap, the boost windows, the rollover,
+        /// the attribution to the parent and the active span. Returns the account accruing
+        /// the trade, the volume and the era recording it
+        fn prepare_trading(
+            trader: &T::AccountId,
+            vol: Volume<T>,
+            at: T::BlockNumber,
+        ) -> Result<(T::AccountId, Volume<T>, Era<T>), DispatchError> {
+            ensure!(
+                !T::StrictTimestamps::get() || at <= frame_system::Pallet::<T>::block_number(),
+                Error::<T>::FutureEra
+            );
+            let block = at;
+            let at = Self::era_of(at).start();
+            ensure!(!Self::is_finalized(at), Error::<T>::EraFinalized);
+            Self::note_trade(trader, vol, block)?;
+            let vol = Self::boosted(vol, block)?;
+            let at = if T::VolumeRollover::get() {
+                Self::rollover_era(at, vol)
+            } else {
+                at
+            };
+            // the volume of sub-accounts accrues to their parent
+            let trader = Self::sub_account_parent(trader).unwrap_or_else(|| trader.clone());
+            if vol > T::LargeVolumeThreshold::get() {
+                Self::deposit_event(Event::LargeVolumeObserved(trader.clone(), vol, at));
+            }
+            if T::ActiveBlockWeighting::get() {
+                Self::extend_span(at, &trader, (block, block));
+            }
+            Ok((trader, vol, at))
+        }
+
+        /// count both sides of an excluded self-trade in the total of the era only
+        #[transactional]
+        fn apply_self_trade(both: Volume<T>, at: Era<T>) -> DispatchResult {
+            Volumes::<T>::try_mutate(Self::era_of(at), |v| -> DispatchResult {
+                Ok(*v = v.checked_add(&both).ok_or(Error::<T>::Overflow)?)
+            })?;
+            SelfTradeVolumes::<T>::mutate(at, |v| *v = v.saturating_add(both));
+            Ok(())
+        }
+
+        #[transactional]
+        fn apply_trading(trader: &T::AccountId, vol: Volume<T>, at: Era<T>) -> DispatchResult {
+            Self::ensure_initialized(trader, at);
+            Volumes::<T>::try_mutate(Self::era_of(at), |v| -> DispatchResult {
+                Ok(*v = v.checked_add(&vol).ok_or(Error::<T>::Overflow)?)
+            })?;
+            EraVolumes::<T>::try_mutate(&at, trader, |v| -> DispatchResult {
+                if v.is_zero() {
+                    Participants::<T>::mutate(&at, |n| *n = n.saturating_add(1));
+                }
+                Ok(*v = v.checked_add(&vol).ok_or(Error::<T>::Overflow)?)
+            })?;
+            T::AccrualModel::record(trader, vol, Self::era_of(at))?;
+            Self::deposit_event(Event::VolumeRecorded(
+                trader.clone(),
+                vol,
+                Self::current_era_index(at),
+            ));
+            Ok(())
+        }
+
+        /// share the pool of the closed `era` among the current shares: the accumulated reward
+        /// per share grows by `pool / TotalShares`, scaled by `ACC_REWARD_PRECISION`
+        fn accrue_shared_pool(era: Era<T>) -> DispatchResult {
+            let total_shares: u128 = T::VolumeToU128::convert(Self::total_shares());
+            if total_shares == 0 {
+                return Ok(());
+            }
+            let pool: u128 = Self::emit(Self::traders_pool(era))?.unique_saturated_into();
+            let increment =
+                U256::from(pool) * U256::from(ACC_REWARD_PRECISION) / U256::from(total_shares);
+            AccRewardPerShare::<T>::mutate(|acc| {
+                *acc = acc.saturating_add(increment.min(U256::from(u128::MAX)).low_u128())
+            });
+            Ok(())
+        }
+
+        /// `shares * AccRewardPerShare / ACC_REWARD_PRECISION`
+        fn accrued_of(shares: Volume<T>) -> u128 {
+            let shares: u128 = T::VolumeToU128::convert(shares);
+            let accrued = U256::from(shares) * U256::from(Self::acc_reward_per_share())
+                / U256::from(ACC_REWARD_PRECISION);
+            accrued.min(U256::from(u128::MAX)).low_u128()
+        }
+
+        /// confirm the pending reward of the shares, `accrued - reward_debt`
+        fn harvest_shares(who: &T::AccountId) -> DispatchResult {
+            Shares::<T>::try_mutate(who, |info| -> DispatchResult {
+                let accrued = Self::accrued_of(info.shares);
+                let reward_debt: u128 = info.reward_debt.unique_saturated_into();
+                let pending = accrued.saturating_sub(reward_debt);
+                if pending > 0 {
+                    Rewards::<T>::try_mutate(who, |r| -> DispatchResult {
+                        r.confirmed = r
+                            .confirmed
+                            .checked_add(&pending.into())
+                            .ok_or(Error::<T>::Overflow)?;
+                        Ok(())
+                    })?;
+                }
+                info.reward_debt = accrued.into();
+                Ok(())
+            })
+        }
+
+        fn deposit_shares(who: &T::AccountId, vol: Volume<T>) -> DispatchResult {
+            Self::harvest_shares(who)?;
+            TotalShares::<T>::try_mutate(|total| -> DispatchResult {
+                Ok(*total = total.checked_add(&vol).ok_or(Error::<T>::Overflow)?)
+            })?;
+            Shares::<T>::try_mutate(who, |info| -> DispatchResult {
+                info.shares = info.shares.checked_add(&vol).ok_or(Error::<T>::Overflow)?;
+                info.reward_debt = Self::accrued_of(info.shares).into();
+                Ok(())
+            })
+        }
+
+        /// apply the buffered trades merged by account and era in the order they arrived, a
+        /// merged trade failing to apply is dropped alone like its failed `save_trading` would
+        fn flush_trades() {
+            let buffer = TradeBuffer::<T>::take();
+            let mut index: BTreeMap<(T::AccountId, Era<T>, bool), usize> = BTreeMap::new();
+            let mut merged: Vec<(T::AccountId, Volume<T>, Era<T>, bool)> = Vec::new();
+            for (trader, vol, at, excluded) in buffer.into_iter() {
+                match index.get(&(trader.clone(), at, excluded)) {
+                    Some(&i) => merged[i].1 = merged[i].1.saturating_add(vol),
+                    None => {
+                        index.insert((trader.clone(), at, excluded), merged.len());
+                        merged.push((trader, vol, at, excluded));
+                    }
+                }
+            }
+            for (trader, vol, at, excluded) in merged.into_iter() {
+                let _ = if excluded {
+                    Self::apply_self_trade(vol, at)
+                } else {
+                    Self::apply_trading(&trader, vol, at)
+                };
+            }
+        }
+
+        #[transactional]
+        fn claim_pool_reward(
+            pool_id: T::PoolId,
+            who: &T::AccountId,
+            at: T::BlockNumber,
+        ) -> Result<Balance<T>, DispatchError> {
+            let at = Self::era_of(at).start();
+            let confirmed = Self::rotate_pool_reward(pool_id, at, Zero::zero(), who)?;
+            if confirmed == Zero::zero() {
+                return Ok(Zero::zero());
+            }
+            PoolRewards::<T>::mutate_exists(pool_id, who, |r| {
+                let pending = r.take().unwrap_or_default();
+                if pending.pending_vol > Zero::zero() {
+                    r.replace(Reward {
+                        confirmed: Zero::zero(),
+                        ..pending
+                    });
+                }
+            });
+            T::Asset::try_mutate_account(&T::Asset::native_token_id(), &who, |b| {
+                if T::ClaimAsReserved::get() {
+                    Ok(b.1 += confirmed)
+                } else {
+                    Ok(b.0 += confirmed)
+                }
+            })?;
+            TotalClaimed::<T>::mutate(|c| *c = c.saturating_add(confirmed));
+            Ok(confirmed)
+        }
+
+        /// the same accrual as `rotate_reward` within the state of a single pool
+        fn rotate_pool_reward(
+            pool_id: T::PoolId,
+            at: T::BlockNumber,
+            vol: Volume<T>,
+            account: &T::AccountId,
+        ) -> Result<Balance<T>, DispatchError> {
+            PoolRewards::<T>::try_mutate(
+                pool_id,
+                account,
+                |r| -> Result<Balance<T>, DispatchError> {
+                    Self::confirm_pool_staged(pool_id, r, account)?;
+                    if at == r.last_modify {
+                        r.pending_vol = r
+                            .pending_vol
+                            .checked_add(&vol)
+                            .ok_or(Error::<T>::Overflow)?;
+                    } else {
+                        if r.pending_vol.is_zero() {
+                            // nothing pending, there is no era to settle
+                        } else if !Self::is_final(r.last_modify) {
+                            // the total of the pending era may still grow, its shares are
+                            // only taken from its final total
+                            PoolStagedVolumes::<T>::mutate(
+                                (pool_id, account.clone()),
+                                r.last_modify,
+                                |v| *v = v.saturating_add(r.pending_vol),
+                            );
+                        } else {
+                            let (era, pending_vol) = (r.last_modify, r.pending_vol);
+                            Self::confirm_pool_pending(pool_id, r, account, era, pending_vol)?;
+                        }
+                        r.pending_vol = vol;
+                        r.last_modify = at;
+                    }
+                    Ok(r.confirmed)
+                },
+            )
+        }
+
+        /// confirm the volumes of `account` staged in `pool_id` in the eras closed since
+        fn confirm_pool_staged(
+            pool_id: T::PoolId,
+            r: &mut Reward<Balance<T>, Volume<T>, Era<T>>,
+            account: &T::AccountId,
+        ) -> DispatchResult {
+            let key = (pool_id, account.clone());
+            let staged: Vec<(Era<T>, Volume<T>)> = PoolStagedVolumes::<T>::iter_prefix(&key)
+                .filter(|(era, _)| Self::is_final(*era))
+                .collect();
+            for (era, vol) in staged {
+                PoolStagedVolumes::<T>::remove(&key, era);
+                Self::confirm_pool_pending(pool_id, r, account, era, vol)?;
+            }
+            Ok(())
+        }
+
+        /// confirm the reward of `vol` traded by `account` in the final `era` of `pool_id`,
+        /// from the rate of the pool frozen for the era
+        fn confirm_pool_pending(
+            pool_id: T::PoolId,
+            r: &mut Reward<Balance<T>, Volume<T>, Era<T>>,
+            account: &T::AccountId,
+            era: Era<T>,
+            vol: Volume<T>,
+        ) -> DispatchResult {
+            if Self::is_expired(era) {
+                Self::deposit_event(Event::RewardExpired(
+                    account.clone(),
+                    vol,
+                    Self::current_era_index(era),
+                ));
+                return Ok(());
+            }
+            PoolFinalizedEras::<T>::insert(era, pool_id, true);
+            Self::freeze_pool_rate(pool_id, era);
+            let total_vol = T::VolumeToU128::convert(Self::pool_volumes(pool_id, era));
+            if total_vol == 0 {
+                return Ok(());
+            }
+            let pending_vol = T::VolumeToU128::convert(vol);
+            let era_reward: u128 = Self::pool_rate_of(pool_id, era).unique_saturated_into();
+            let share = Self::plain_share(pending_vol, total_vol).mul_floor(era_reward);
+            let a = Self::emit(share.into())?;
+            r.confirmed = r.confirmed.checked_add(&a).ok_or(Error::<T>::Overflow)?;
+            Ok(())
+        }
+
+        #[transactional]
+        fn rotate_reward(
+            era: EraId<T::BlockNumber>,
+            vol: Volume<T>,
+            account: &T::AccountId,
+        ) -> Result<Balance<T>, DispatchError> {
+            // the volume of a rolled over era is pending in the era taking it, which must not
+            // be settled by a claim within the rest of the rolled over window
+            let at = if T::VolumeRollover::get() {
+                Self::recording_era(era.start())
+            } else {
+                era.start()
+            };
+            Rewards::<T>::try_mutate(account, |r| -> Result<Balance<T>, DispatchError> {
+                let before = r.pending_vol;
+                Self::confirm_staged(r, account)?;
+                if at == r.last_modify {
+                    r.pending_vol = r
+                        .pending_vol
+                        .checked_add(&vol)
+                        .ok_or(Error::<T>::Overflow)?;
+                } else {
+                    // nothing pending, including the default entry of a new account whose
+                    // `last_modify` is 0, so there is no era to settle
+                    if r.pending_vol == Zero::zero() {
+                        r.pending_vol = vol;
+                        r.last_modify = at;
+                    } else if !Self::is_final(r.last_modify) || Self::is_supply_capped(account) {
+                        // the total of the pending era may still grow, so the shares of the
+                        // era are only taken from its final total, all summing to the pool.
+                        // Above `MaxIssuance` the final era waits the same way to be settled
+                        // once the supply drops under the cap again.
+                        StagedVolumes::<T>::mutate(account, r.last_modify, |v| {
+                            *v = v.saturating_add(r.pending_vol)
+                        });
+                        r.pending_vol = vol;
+                        r.last_modify = at;
+                    } else {
+                        let (era, pending_vol) = (r.last_modify, r.pending_vol);
+                        Self::confirm_pending(r, account, era, pending_vol)?;
+                        r.pending_vol = vol;
+                        r.last_modify = at;
+                    }
+                }
+                Self::adjust_pending(before, r.pending_vol);
+                Ok(r.confirmed)
+            })
+        }
+
+        /// count `vol` traded by `trader` at the block `at` in the volume of the current block,
+        /// capped by `MaxVolumePerBlock`
+        fn note_trade(trader: &T::AccountId, vol: Volume<T>, at: T::BlockNumber) -> DispatchResult {
+            let block_vol = Self::block_volume()
+                .checked_add(&vol)
+                .ok_or(Error::<T>::Overflow)?;
+            ensure!(
+                block_vol <= T::MaxVolumePerBlock::get(),
+                Error::<T>::BlockVolumeCapExceeded
+            );
+            BlockVolume::<T>::put(block_vol);
+            LastTradeBlock::<T>::insert(trader, at);
+            Ok(())
+        }
+
+        /// whether `era` was settled, or pruned along with its `FinalizedEras` flag
+        fn is_finalized(era: Era<T>) -> bool {
+            era < Self::prune_cursor() || Self::finalized_eras(era)
+        }
+
+        /// whether `era` of `pool_id` was settled, or pruned along with its flag
+        fn is_pool_finalized(pool_id: T::PoolId, era: Era<T>) -> bool {
+            era < Self::prune_cursor() || Self::pool_finalized_eras(era, pool_id)
+        }
+
+        /// whether `era` is older than the `HistoryDepth` eras retained
+        fn is_expired(era: Era<T>) -> bool {
+            let now = frame_system::Pallet::<T>::block_number();
+            let retained = Self::era_duration().saturating_mul(T::HistoryDepth::get().into());
+            era < Self::era_of(now).start().saturating_sub(retained)
+        }
+
+        /// whether the total of `era` can't change anymore
+        fn is_final(era: Era<T>) -> bool {
+            Self::is_era_closed(era, frame_system::Pallet::<T>::block_number())
+                || Self::rolled_over_eras(era)
+        }
+
+        /// whether the supply of the reward token is above `MaxIssuance`, the rewards confirmed
+        /// without a pool being minted on claim outside of the total issuance
+        fn is_supply_capped(account: &T::AccountId) -> bool {
+            let issuance = T::Asset::total_issuance(&T::Asset::native_token_id());
+            let supply = if T::FundedEmission::get() {
+                issuance
+            } else {
+                issuance.saturating_add(Self::total_distributed())
+            };
+            if supply <= T::MaxIssuance::get() {
+                return false;
+            }
+            Self::deposit_event(Event::AccrualSuspendedForSupply(account.clone(), supply));
+            true
+        }
+
+        /// confirm the volumes of `account` staged in the eras closed since
+        fn confirm_staged(
+            r: &mut Reward<Balance<T>, Volume<T>, Era<T>>,
+            account: &T::AccountId,
+        ) -> DispatchResult {
+            let staged: Vec<(Era<T>, Volume<T>)> = StagedVolumes::<T>::iter_prefix(account)
+                .filter(|(era, _)| Self::is_final(*era))
+                .collect();
+            if staged.is_empty() || Self::is_supply_capped(account) {
+                return Ok(());
+            }
+            for (era, vol) in staged {
+                StagedVolumes::<T>::remove(account, era);
+                Self::confirm_pending(r, account, era, vol)?;
+            }
+            Ok(())
+        }
+
+        /// confirm the reward of `vol` traded by `account` in the final `era`
+        fn confirm_pending(
+            r: &mut Reward<Balance<T>, Volume<T>, Era<T>>,
+            account: &T::AccountId,
+            era: Era<T>,
+            vol: Volume<T>,
+        ) -> DispatchResult {
+            // the totals of a pruned era are gone, its volume can't be settled anymore
+            if Self::is_expired(era) {
+                Self::deposit_event(Event::RewardExpired(
+                    account.clone(),
+                    vol,
+                    Self::current_era_index(era),
+                ));
+                return Ok(());
+            }
+            let share = Self::era_reward_of(era, vol, account)?;
+            let a = share.saturating_sub(Self::credit_referrer(account, share)?);
+            r.confirmed = r.confirmed.checked_add(&a).ok_or(Error::<T>::Overflow)?;
+            if !a.is_zero() {
+                EraRewards::<T>::mutate(era, account, |e| *e = e.saturating_add(a));
+            }
+            Self::deposit_event(Event::RewardConfirmed(
+                account.clone(),
+                a,
+                Self::current_era_index(era),
+            ));
+            Ok(())
+        }
+
+        /// referral cuts are taken out of the share of the trader, so the confirmations of an
+        /// era never sum to more than its pool while the referrer needs no volume and the
+        /// `Volumes` stay untouched. The chain is walked up to `ReferralLevels` and stops at the
+        /// first referrer already visited. Returns the cuts paid.
+        fn credit_referrer(
+            account: &T::AccountId,
+            reward: Balance<T>,
+        ) -> Result<Balance<T>, DispatchError> {
+            let reward: u128 = reward.unique_saturated_into();
+            let mut headroom = T::MaxReferralShare::get().mul_floor(reward);
+            let level_shares = T::ReferralLevelShares::get();
+            let mut visited = sp_std::vec![account.clone()];
+            for level in 0..T::ReferralLevels::get() as usize {
+                let referrer = match Self::referrer(&visited[level]) {
+                    Some(referrer) if !visited.contains(&referrer) => referrer,
+                    _ => break,
+                };
+                let share = match level {
+                    0 => T::ReferralShare::get(),
+                    _ => level_shares.get(level - 1).copied().unwrap_or_default(),
+                };
+                let cut: Balance<T> = share.mul_floor(reward).min(headroom).into();
+                if !cut.is_zero() {
+                    Rewards::<T>::try_mutate(&referrer, |r| -> DispatchResult {
+                        r.confirmed = r.confirmed.checked_add(&cut).ok_or(Error::<T>::Overflow)?;
+                        Ok(())
+                    })?;
+                }
+                headroom = headroom.saturating_sub(cut.unique_saturated_into());
+                visited.push(referrer);
+            }
+            let max: u128 = T::MaxReferralShare::get().mul_floor(reward);
+            Ok(max.saturating_sub(headroom).into())
+        }
+
+        /// remove up to `vol` of the pending volume in the era of `at`, the era total drops by
+        /// exactly the removed amount so it always equals the sum of the pending volumes
+        fn remove_volume(
+            trader: &T::AccountId,
+            vol: Volume<T>,
+            at: T::BlockNumber,
+        ) -> Result<Volume<T>, DispatchError> {
+            let at = Self::era_of(at).start();
+            ensure!(!Self::is_finalized(at), Error::<T>::EraFinalized);
+            let trader = Self::sub_account_parent(trader).unwrap_or_else(|| trader.clone());
+            let removed = T::AccrualModel::withdraw(&trader, vol, Self::era_of(at))?;
+            if removed.is_zero() {
+                return Ok(removed);
+            }
+            let left = Self::era_volumes(at, &trader).saturating_sub(removed);
+            if left.is_zero() {
+                EraVolumes::<T>::remove(at, &trader);
+                Participants::<T>::mutate(at, |n| *n = n.saturating_sub(1));
+            } else {
+                EraVolumes::<T>::insert(at, &trader, left);
+            }
+            Volumes::<T>::mutate(Self::era_of(at), |v| *v = v.saturating_sub(removed));
+            Self::deposit_event(Event::VolumeRevoked(trader, removed));
+            Ok(removed)
+        }
+
+        /// every confirmation counts towards `MaxTotalEmission`, the one crossing the cap only
+        /// confirms the remaining headroom, and the one draining a funded pool only what's left
+        fn emit(amount: Balance<T>) -> Result<Balance<T>, DispatchError> {
+            TotalDistributed::<T>::try_mutate(|total| -> Result<Balance<T>, DispatchError> {
+                let headroom = T::MaxTotalEmission::get().saturating_sub(*total);
+                let a = amount.min(headroom);
+                if !headroom.is_zero() && a == headroom {
+                    Self::deposit_event(Event::EmissionCapReached);
+                }
+                let a = if T::FundedEmission::get() {
+                    Self::draw_pool(a)?
+                } else {
+                    a
+                };
+                *total = total.checked_add(&a).ok_or(Error::<T>::Overflow)?;
+                Ok(a)
+            })
+        }
+
+        /// remove the data of the eras out of the `HistoryDepth` window from the `PruneCursor` on,
+        /// at most `MaxPrunePerBlock` entries per block, so a backlog of expired eras is spread
+        /// over the following blocks
+        fn prune_expired(now: T::BlockNumber) -> Weight {
+            let window = Self::era_duration()
+                .saturating_mul(T::HistoryDepth::get().saturating_add(1).into());
+            if now < window {
+                return 0;
+            }
+            let expired = Self::era_of(now - window).start();
+            let mut budget = T::MaxPrunePerBlock::get();
+            let mut cursor = Self::prune_cursor();
+            let mut writes = 0u64;
+            // the maps keyed by the era first, drained each within the budget
+            let prefixes: [fn(Era<T>, u32) -> sp_io::KillStorageResult; 7] = [
+                |era, limit| EraRewards::<T>::remove_prefix(era, Some(limit)),
+                |era, limit| ActiveSpans::<T>::remove_prefix(era, Some(limit)),
+                |era, limit| PreciseShares::<T>::remove_prefix(era, Some(limit)),
+                |era, limit| EraVolumes::<T>::remove_prefix(era, Some(limit)),
+                |era, limit| Allocations::<T>::remove_prefix(era, Some(limit)),
+                |era, limit| PoolFinalizedEras::<T>::remove_prefix(era, Some(limit)),
+                |era, limit| PoolEraRates::<T>::remove_prefix(era, Some(limit)),
+            ];
+            'eras: while cursor <= expired && budget > 0 {
+                for remove in prefixes.iter() {
+                    let (removed, done) = Self::drain(remove(cursor, budget));
+                    budget = budget.saturating_sub(removed);
+                    writes = writes.saturating_add(removed.into());
+                    if !done || budget == 0 {
+                        break 'eras;
+                    }
+                }
+                Volumes::<T>::remove(EraId(cursor));
+                WeightedTotals::<T>::remove(cursor);
+                RolledOverEras::<T>::remove(cursor);
+                Participants::<T>::remove(cursor);
+                EraDistributed::<T>::remove(cursor);
+                FinalizedEras::<T>::remove(cursor);
+                AllocatedEras::<T>::remove(cursor);
+                EraDust::<T>::remove(cursor);
+                OperatorPaid::<T>::remove(cursor);
+                EraBonusPool::<T>::remove(cursor);
+                EraParams::<T>::remove(cursor);
+                SelfTradeVolumes::<T>::remove(cursor);
+                ReconcileProgress::<T>::remove(cursor);
+                writes = writes.saturating_add(13);
+                // the pools and the pairs are few, their volumes are keyed by them first
+                for pool_id in KnownPools::<T>::iter_keys() {
+                    PoolVolumes::<T>::remove(pool_id, cursor);
+                    writes = writes.saturating_add(1);
+                }
+                for pair_id in KnownPairs::<T>::iter_keys() {
+                    PairVolumes::<T>::remove(pair_id, cursor);
+                    writes = writes.saturating_add(1);
+                }
+                budget -= 1;
+                // the cursor stays on the era boundaries across the changes of the duration
+                cursor = Self::next_era(cursor);
+            }
+            PruneCursor::<T>::put(cursor);
+            T::DbWeight::get().reads_writes(1, writes.saturating_add(1))
+        }
+
+        /// the entries removed from a prefix and whether none are left
+        fn drain(result: sp_io::KillStorageResult) -> (u32, bool) {
+            match result {
+                sp_io::KillStorageResult::AllRemoved(removed) => (removed, true),
+                sp_io::KillStorageResult::SomeRemaining(removed) => (removed, false),
+            }
+        }
+
+        /// the era taking `vol` traded in `era`, the first one from `era` on neither rolled
+        /// over nor overflowed by it. An era overflowed is rolled over, so its total and the
+        /// shares settled from it early stay final.
+        fn rollover_era(era: Era<T>, vol: Volume<T>) -> Era<T> {
+            let mut era = era;
+            loop {
+                let overflowed = Self::volumes(Self::era_of(era)).checked_add(&vol).is_none();
+                if !Self::rolled_over_eras(era) && !overflowed {
+                    return era;
+                }
+                let next = Self::next_era(era);
+                if !Self::rolled_over_eras(era) {
+                    RolledOverEras::<T>::insert(era, true);
+                    Self::deposit_event(Event::EraRolledOver(era, next));
+                }
+                era = next;
+            }
+        }
+
+        /// the era recording the volume traded in `era`, past the rolled over ones
+        fn recording_era(era: Era<T>) -> Era<T> {
+            let mut era = era;
+            while Self::rolled_over_eras(era) {
+                era = Self::next_era(era);
+            }
+            era
+        }
+
+        /// the funded emission leaves the pool account as it's confirmed, so the claim crediting
+        /// it later only gives back what was funded
+        fn draw_pool(amount: Balance<T>) -> Result<Balance<T>, DispatchError> {
+            let balance = Self::pool_balance();
+            let drawn = amount.min(balance);
+            if drawn < amount {
+                Self::deposit_event(Event::PoolDepleted(amount - drawn));
+            }
+            T::Asset::try_mutate_account(
+                &T::Asset::native_token_id(),
+                &Self::pool_account(),
+                |b| -> DispatchResult {
+                    b.0 =
+                        b.0.checked_sub(&drawn)
+                            .ok_or(Error::<T>::InsufficientBalance)?;
+                    Ok(())
+                },
+            )?;
+            PoolBalance::<T>::put(balance - drawn);
+            Ok(drawn)
+        }
+
+        /// the reward of `pending_vol` in the final `era`, the shares are taken from the final
+        /// total of the era so its confirmations sum to its pool at most
+        fn era_reward_of(
+            era: Era<T>,
+            pending_vol: Volume<T>,
+            account: &T::AccountId,
+        ) -> Result<Balance<T>, DispatchError> {
+            // the pending era is keyed as it was stored, which stays reachable under the
+            // alignment of an older `EraDuration`
+            let total_vol = T::VolumeToU128::convert(Volumes::<T>::get(EraId(era)));
+            // the total of an era out of the retained ones is pruned by `on_initialize`, the
+            // expired pending volume confirms nothing
+            let now = Self::era_of(frame_system::Pallet::<T>::block_number()).start();
+            let retained = Self::era_duration().saturating_mul(T::HistoryDepth::get().into());
+            if total_vol == 0 && era < now.saturating_sub(retained) {
+                return Ok(Zero::zero());
+            }
+            ensure!(total_vol > 0, Error::<T>::DivideByZero);
+            // the confirmed shares must stay consistent with the ones settled later
+            if Self::is_era_closed(era, frame_system::Pallet::<T>::block_number()) {
+                FinalizedEras::<T>::insert(era, true);
+            }
+            Self::freeze_params(era);
+            let era_reward: u128 = Self::traders_pool(era).unique_saturated_into();
+            let mut share: Balance<T> = if T::ExactDistribution::get()
+                && Self::participants(era) <= T::MaxParticipants::get()
+            {
+                if !AllocatedEras::<T>::get(era) {
+                    Self::allocate_era(era, total_vol, era_reward);
+                }
+                Allocations::<T>::take(era, account)
+            } else {
+                let pending_vol = T::VolumeToU128::convert(pending_vol);
+                if T::RecordShares::get() && !Self::is_weighted(era) {
+                    PreciseShares::<T>::insert(
+                        era,
+                        account,
+                        Self::plain_share(pending_vol, total_vol),
+                    );
+                }
+                Self::share_of(era, account, pending_vol, total_vol, era_reward).into()
+            };
+            let unit = T::RoundToUnit::get();
+            if !unit.is_zero() {
+                let dust = share % unit;
+                if !dust.is_zero() {
+                    share = share - dust;
+                    EraDust::<T>::mutate(era, |d| *d = d.saturating_add(dust));
+                }
+            }
+            EraDistributed::<T>::try_mutate(
+                era,
+                |distributed| -> Result<Balance<T>, DispatchError> {
+                    // the shares are taken pro rata from the final total, so they sum to the
+                    // pool at most; the headroom only guards the rounding of the odd modes
+                    let headroom = Balance::<T>::from(era_reward).saturating_sub(*distributed);
+                    let a = Self::emit(share.min(headroom))?;
+                    *distributed = distributed.checked_add(&a).ok_or(Error::<T>::Overflow)?;
+                    EpochDistributed::<T>::mutate(Self::epoch_of(era), |d| {
+                        *d = d.saturating_add(a)
+                    });
+                    Ok(a)
+                },
+            )
+        }
+
+        /// whether the shares of `era` are weighted by the active blocks
+        fn is_weighted(era: Era<T>) -> bool {
+            T::ActiveBlockWeighting::get() && Self::participants(era) <= T::MaxParticipants::get()
+        }
+
+        /// the part of the pool of an era taken by `vol` of its `total_vol`
+        fn plain_share(vol: u128, total_vol: u128) -> Perquintill {
+            Perquintill::from_rational(vol, total_vol.max(T::MinDenominator::get()))
+        }
+
+        /// the share of `vol` traded by `who` in the pool of the final `era` before it's rounded
+        /// to `RoundToUnit`, for every mode but the exact distribution
+        fn share_of(
+            era: Era<T>,
+            who: &T::AccountId,
+            vol: u128,
+            total_vol: u128,
+            era_reward: u128,
+        ) -> u128 {
+            if Self::is_weighted(era) {
+                Self::weighted_share(era, who, vol, era_reward)
+            } else {
+                // `mul_floor` multiplies the quotient and the remainder of `era_reward` by the
+                // parts separately, so it's exact to the unit over the whole u128 range, and the
+                // floored shares can't sum above the pool as the nearest rounding of `*` could
+                Self::plain_share(vol, total_vol).mul_floor(era_reward)
+            }
+        }
+
+        /// the shares of the participants of the final `era` from the `start`th on, at most
+        /// `count` of them, as its settlement confirms them, rounded to `RoundToUnit`
+        fn era_allocation(
+            era: Era<T>,
+            total_vol: u128,
+            era_reward: u128,
+            start: usize,
+            count: usize,
+        ) -> Vec<(T::AccountId, u128)> {
+            let shares: Vec<(T::AccountId, u128)> = if T::ExactDistribution::get()
+                && Self::participants(era) <= T::MaxParticipants::get()
+            {
+                Self::largest_remainder(era, total_vol, era_reward)
+                    .into_iter()
+                    .skip(start)
+                    .take(count)
+                    .map(|(who, amount)| (who, amount.unique_saturated_into()))
+                    .collect()
+            } else {
+                EraVolumes::<T>::iter_prefix(&era)
+                    .skip(start)
+                    .take(count)
+                    .map(|(who, vol)| {
+                        let vol = T::VolumeToU128::convert(vol);
+                        let share = Self::share_of(era, &who, vol, total_vol, era_reward);
+                        (who, share)
+                    })
+                    .collect()
+            };
+            let unit: u128 = T::RoundToUnit::get().unique_saturated_into();
+            shares
+                .into_iter()
+                .map(|(who, share)| match unit {
+                    0 => (who, share),
+                    unit => (who, share - share % unit),
+                })
+                .collect()
+        }
+
+        /// widen the span `who` was active within `era` to cover `span`
+        fn extend_span(era: Era<T>, who: &T::AccountId, span: (T::BlockNumber, T::BlockNumber)) {
+            ActiveSpans::<T>::mutate(era, who, |active| {
+                *active = Some(match *active {
+                    Some((first, last)) => (first.min(span.0), last.max(span.1)),
+                    None => span,
+                })
+            });
+        }
+
+        /// the first and the last block of `era`
+        fn era_span(era: Era<T>) -> (T::BlockNumber, T::BlockNumber) {
+            (era, Self::next_era(era) - One::one())
+        }
+
+        /// the blocks `who` was active within `era`. Every path crediting a volume records its
+        /// span, so a volume without one has no active block and weighs nothing
+        fn active_blocks(era: Era<T>, who: &T::AccountId) -> u128 {
+            match Self::active_spans(era, who) {
+                Some((first, last)) => {
+                    let blocks: u128 = (last - first).unique_saturated_into();
+                    blocks.saturating_add(1)
+                }
+                None => 0,
+            }
+        }
+
+        /// the share of `who` in the pool of `era` with the volumes weighted by their active
+        /// blocks, the `MinDenominator` floor weighted as if active for the whole era
+        fn weighted_share(
+            era: Era<T>,
+            who: &T::AccountId,
+            pending_vol: u128,
+            era_reward: u128,
+        ) -> u128 {
+            let total = Self::weighted_totals(era).unwrap_or_else(|| {
+                let total = EraVolumes::<T>::iter_prefix(&era).fold(U256::zero(), |t, (w, v)| {
+                    let v = T::VolumeToU128::convert(v);
+                    t.saturating_add(U256::from(v) * U256::from(Self::active_blocks(era, &w)))
+                });
+                let total = total.min(U256::from(u128::MAX)).as_u128();
+                WeightedTotals::<T>::insert(era, total);
+                total
+            });
+            if total == 0 {
+                return 0;
+            }
+            let blocks: u128 = (Self::next_era(era) - era).unique_saturated_into();
+            let total =
+                U256::from(total).max(U256::from(T::MinDenominator::get()) * U256::from(blocks));
+            let weighted = U256::from(pending_vol) * U256::from(Self::active_blocks(era, who));
+            let share = weighted * U256::from(era_reward) / total;
+            share.min(U256::from(era_reward)).as_u128()
+        }
+
+        fn allocate_era(era: Era<T>, total_vol: u128, era_reward: u128) {
+            for (who, amount) in Self::largest_remainder(era, total_vol, era_reward) {
+                Allocations::<T>::insert(&era, &who, amount);
+            }
+            AllocatedEras::<T>::insert(&era, true);
+        }
+
+        /// largest-remainder: floor every share, then hand out the leftover units one by one
+        /// to the participants with the largest remainders, of a total floored at
+        /// `MinDenominator`
+        fn largest_remainder(
+            era: Era<T>,
+            total_vol: u128,
+            era_reward: u128,
+        ) -> Vec<(T::AccountId, Balance<T>)> {
+            let total_vol = U256::from(total_vol.max(T::MinDenominator::get()));
+            let era_reward_u256 = U256::from(era_reward);
+            let mut shares: Vec<(T::AccountId, u128, U256, u128)> =
+                EraVolumes::<T>::iter_prefix(&era)
+                    .map(|(who, vol)| {
+                        let vol = T::VolumeToU128::convert(vol);
+                        let product = era_reward_u256 * U256::from(vol);
+                        (
+                            who,
+                            (product / total_vol).low_u128(),
+                            product % total_vol,
+                            vol,
+                        )
+                    })
+                    .collect();
+            let floored = shares
+                .iter()
+                .fold(0u128, |acc, share| acc.saturating_add(share.1));
+            // only the rounding dust of the floored shares is left over, below one unit per
+            // participant, the part of the pool of the volume out of `EraVolumes` isn't
+            let participating = shares
+                .iter()
+                .fold(0u128, |acc, share| acc.saturating_add(share.3));
+            let target = (era_reward_u256 * U256::from(participating) / total_vol)
+                .min(U256::from(era_reward))
+                .low_u128();
+            let mut leftover = target.saturating_sub(floored);
+            if T::DustLottery::get() && leftover > 0 && participating > 0 {
+                let (seed, _) = T::Randomness::random(&(b"fuso/reward/dust", era).encode());
+                let seed =
+                    u128::decode(&mut TrailingZeroInput::new(seed.as_ref())).unwrap_or_default();
+                // the winner is the participant whose volume range covers the drawn ticket
+                let mut ticket = seed % participating;
+                let winner = shares.iter().position(|share| {
+                    if ticket < share.3 {
+                        return true;
+                    }
+                    ticket -= share.3;
+                    false
+                });
+                if let Some(winner) = winner {
+                    shares[winner].1 = shares[winner].1.saturating_add(leftover);
+                }
+                return shares
+                    .into_iter()
+                    .map(|(who, amount, _, _)| (who, amount.into()))
+                    .collect();
+            }
+            shares.sort_by(|a, b| b.2.cmp(&a.2));
+            shares
+                .into_iter()
+                .map(|(who, amount, _, _)| {
+                    let amount = if leftover > 0 {
+                        leftover -= 1;
+                        amount + 1
+                    } else {
+                        amount
+                    };
+                    (who, amount.into())
+                })
+                .collect()
+        }
+    }
+
+    impl<T: Config> Rewarding<T::AccountId, Volume<T>, T::BlockNumber> for Pallet<T>
+    where
+        Balance<T>: From<u128>,
+    {
+        type Balance = Balance<T>;
+
+        /// the duration of the current era
+        fn era_duration() -> T::BlockNumber {
+            Self::alignment_of(frame_system::Pallet::<T>::block_number()).1
+        }
+
+        fn total_volume(at: T::BlockNumber) -> Volume<T> {
+            Self::volumes(Self::era_of(at))
+        }
+
+        fn acked_reward(who: &T::AccountId) -> Self::Balance {
+            Self::rewards(who).confirmed
+        }
+
+        fn save_trading_weight(trader: &T::AccountId, at: T::BlockNumber) -> Weight {
+            let trader = Self::sub_account_parent(trader).unwrap_or(trader.clone());
+            let reward = Self::rewards(&trader);
+            // only a pending volume of another era needs to be settled
+            if reward.pending_vol.is_zero() || reward.last_modify == Self::era_of(at).start() {
+                T::WeightInfo::save_trading_same_era()
+            } else {
+                T::WeightInfo::save_trading_rotate()
+            }
+        }
+
+        /// the KYC hold is the only per-account gate, checked on the account and on the parent
+        /// its volume accrues to
+        fn is_eligible(who: &T::AccountId) -> bool {
+            if Self::kyc_hold(who) {
+                return false;
+            }
+            Self::sub_account_parent(who).map_or(true, |parent| !Self::kyc_hold(&parent))
+        }
+
+        fn confirm_volume_checkpoint(
+            era: T::BlockNumber,
+            expected_total: Volume<T>,
+        ) -> DispatchResult {
+            let era = Self::era_of(era);
+            ensure!(
+                Self::volumes(era) == expected_total,
+                Error::<T>::CheckpointMismatch
+            );
+            Self::deposit_event(Event::VolumeCheckpointConfirmed(
+                era.start(),
+                expected_total,
+            ));
+            Ok(())
+        }
+
+        fn is_era_closed(era: T::BlockNumber, at: T::BlockNumber) -> bool {
+            Self::next_era(era) <= at
+        }
+
+        fn era_share(who: &T::AccountId, era: T::BlockNumber) -> Perquintill {
+            let era = Self::era_of(era).start();
+            let now = frame_system::Pallet::<T>::block_number();
+            if !Self::is_era_closed(era, now) {
+                return Zero::zero();
+            }
+            let total_vol = T::VolumeToU128::convert(Self::volumes(Self::era_of(era)));
+            if total_vol == 0 {
+                return Zero::zero();
+            }
+            let vol = T::VolumeToU128::convert(Self::era_volumes(era, who));
+            Perquintill::from_rational(vol, total_vol)
+        }
+
+        #[transactional]
+        fn save_trading(
+            trader: &T::AccountId,
+            vol: Volume<T>,
+            at: T::BlockNumber,
+        ) -> DispatchResult {
+            if vol == Zero::zero() {
+                return Ok(());
+            }
+            let (trader, vol, at) = Self::prepare_trading(trader, vol, at)?;
+            let vol = Self::early_bird(&trader, vol, at)?;
+            if T::BatchSettlement::get() {
+                TradeBuffer::<T>::append((trader, vol, at, false));
+                return Ok(());
+            }
+            Self::apply_trading(&trader, vol, at)
+        }
+
+        /// the volume only accrues shares and never touches the balances, so trading the native
+        /// token doesn't interfere with the rewards credited in it at claim
+        fn save_native_trading(
+            trader: &T::AccountId,
+            vol: Volume<T>,
+            at: T::BlockNumber,
+        ) -> DispatchResult {
+            if T::ExcludeNativeTrades::get() {
+                return Ok(());
+            }
+            Self::save_trading(trader, vol, at)
+        }
+
+        /// a self-trade still counts on both sides in `Volumes`, like the fills of two parties,
+        /// so the shares of the others are as if it were eligible. It goes through the same
+        /// checks, boosts and batching as any trade, only never in the `EraVolumes`
+        #[transactional]
+        fn save_trading_pair(
+            maker: &T::AccountId,
+            taker: &T::AccountId,
+            vol: Volume<T>,
+            at: T::BlockNumber,
+        ) -> DispatchResult {
+            if maker != taker || !T::ExcludeSelfTrades::get() {
+                Self::save_trading(maker, vol, at)?;
+                return Self::save_trading(taker, vol, at);
+            }
+            if vol == Zero::zero() {
+                return Ok(());
+            }
+            let both = vol.checked_add(&vol).ok_or(Error::<T>::Overflow)?;
+            // counted in the total like any trade but never accrued, so no early bird
+            let (trader, both, at) = Self::prepare_trading(maker, both, at)?;
+            Self::deposit_event(Event::SelfTradeExcluded(maker.clone(), vol));
+            if T::BatchSettlement::get() {
+                TradeBuffer::<T>::append((trader, both, at, true));
+                return Ok(());
+            }
+            Self::apply_self_trade(both, at)
+        }
+
+        #[transactional]
+        fn revoke_trading(
+            trader: &T::AccountId,
+            vol: Volume<T>,
+            at: T::BlockNumber,
+        ) -> DispatchResult {
+            Self::remove_volume(trader, vol, at).map(|_| ())
+        }
+
+        fn save_holding(
+            trader: &T::AccountId,
+            position: Volume<T>,
+            duration: T::BlockNumber,
+            at: T::BlockNumber,
+        ) -> DispatchResult {
+            let position: u128 = T::VolumeToU128::convert(position);
+            let duration: u128 = duration.unique_saturated_into();
+            let vol = position.checked_mul(duration).ok_or(Error::<T>::Overflow)?;
+            ensure!(
+                vol <= T::VolumeToU128::convert(Bounded::max_value()),
+                Error::<T>::Overflow
+            );
+            Self::save_trading(trader, T::U128ToVolume::convert(vol), at)
+        }
+    }
+
+    /// the rewards of a reaped account can't be claimed anymore, the confirmed ones, of the
+    /// default pool and of the category pools, are given back to the emission, or to the pool
+    /// they were drawn from, while its pending and staged volumes stay in the totals of their
+    /// eras so the shares of the others are unchanged. Its shares leave the shared pool, its
+    /// virtual volume is no longer credited and its sub-accounts accrue to themselves again.
+    impl<T: Config> OnKilledAccount<T::AccountId> for Pallet<T> {
+        fn on_killed_account(who: &T::AccountId) {
+            Self::detach_sub_account(who);
+            SubAccountRequests::<T>::remove(who);
+            for (sub, _) in SubAccounts::<T>::drain_prefix(who) {
+                SubAccountParent::<T>::remove(&sub);
+            }
+            Referrer::<T>::remove(who);
+            Registrations::<T>::remove(who);
+            CrankMaxTip::<T>::remove(who);
+            Vesting::<T>::remove(who);
+            StagedVolumes::<T>::remove_prefix(who, None);
+            VirtualVolume::<T>::remove(who);
+            KycHold::<T>::remove(who);
+            RewardLocks::<T>::remove(who);
+            LastClaim::<T>::remove(who);
+            LastTradeBlock::<T>::remove(who);
+            if Shares::<T>::contains_key(who) {
+                // the accrued reward of the shares is forfeited along with the confirmed one
+                let _ = Self::harvest_shares(who);
+                let info = Shares::<T>::take(who);
+                TotalShares::<T>::mutate(|total| *total = total.saturating_sub(info.shares));
+            }
+            let mut forfeited: Balance<T> = Zero::zero();
+            let mut rewarded = false;
+            for pool_id in KnownPools::<T>::iter_keys() {
+                PoolStagedVolumes::<T>::remove_prefix((pool_id, who.clone()), None);
+                if PoolRewards::<T>::contains_key(pool_id, who) {
+                    let reward = PoolRewards::<T>::take(pool_id, who);
+                    forfeited = forfeited.saturating_add(reward.confirmed);
+                    rewarded = true;
+                }
+            }
+            if Rewards::<T>::contains_key(who) {
+                let reward = Rewards::<T>::take(who);
+                Self::adjust_pending(reward.pending_vol, Zero::zero());
+                forfeited = forfeited.saturating_add(reward.confirmed);
+                rewarded = true;
+            }
+            if !rewarded {
+                return;
+            }
+            TotalDistributed::<T>::mutate(|total| *total = total.saturating_sub(forfeited));
+            if T::FundedEmission::get() && !forfeited.is_zero() {
+                let refunded = T::Asset::try_mutate_account(
+                    &T::Asset::native_token_id(),
+                    &Self::pool_account(),
+                    |b| -> DispatchResult {
+                        b.0 = b.0.checked_add(&forfeited).ok_or(Error::<T>::Overflow)?;
+                        Ok(())
+                    },
+                );
+                if refunded.is_ok() {
+                    PoolBalance::<T>::mutate(|p| *p = p.saturating_add(forfeited));
+                }
+            }
+            Self::deposit_event(Event::RewardForfeited(who.clone(), forfeited));
         }
     }
 }
@@ -1,12 +1,13 @@
 use super::*;
 use crate as pallet_fuso_reward;
-use frame_support::parameter_types;
+use frame_support::{parameter_types, traits::Randomness, PalletId};
 use frame_system as system;
+use fuso_support::traits::Token;
 use sp_runtime::traits::{IdentifyAccount, Verify};
 use sp_runtime::{
     generic,
     traits::{AccountIdLookup, BlakeTwo256},
-    MultiSignature,
+    DispatchError, DispatchResult, MultiSignature, Perquintill,
 };
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
@@ -16,6 +17,8 @@ pub(crate) type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::
 pub(crate) type BlockNumber = u32;
 pub type Signature = MultiSignature;
 pub type Balance = u128;
+/// narrower than the balance, so the volumes are never mistaken for balances
+pub type Volume = u64;
 pub type Moment = u64;
 pub type Index = u64;
 pub type Hash = sp_core::H256;
@@ -50,7 +53,7 @@ impl frame_system::Config for Test {
     type Header = generic::Header<BlockNumber, BlakeTwo256>;
     type Index = Index;
     type Lookup = AccountIdLookup<AccountId, ()>;
-    type OnKilledAccount = ();
+    type OnKilledAccount = RewardModule;
     type OnNewAccount = ();
     type OnSetCode = ();
     type Origin = Origin;
@@ -90,14 +93,258 @@ impl pallet_fuso_token::Config for Test {
 
 parameter_types! {
     pub const EraDuration: BlockNumber = 100;
-    pub const RewardsPerEra: Balance = 1000000000000000000000000;
+    pub const MaxParticipants: u32 = 10;
+    pub const FreeClaimWindow: BlockNumber = 10;
+    pub const HistoryDepth: u32 = 3;
+    pub const ErasPerEpoch: u32 = 2;
+    pub const MaxImport: u32 = 3;
+    pub const MaxBatchQuery: u32 = 3;
+    pub const MaxHistoryRange: u32 = 3;
+    pub const RegistrationDeposit: Balance = 1 * DOLLARS;
+    pub static RewardsPerEra: Balance = 1000000000000000000000000;
+    pub ReferralShare: Perquintill = Perquintill::from_percent(10);
+    pub static ExactDistribution: bool = false;
+    pub static ClaimAsReserved: bool = false;
+    pub static StrictTimestamps: bool = false;
+    pub static ExcludeNativeTrades: bool = false;
+    pub static DustLottery: bool = false;
+    pub static BatchSettlement: bool = false;
+    pub static MinEraReward: Balance = 0;
+    pub OperatorAccount: AccountId = sp_keyring::AccountKeyring::Eve.into();
+    pub static OperatorShare: Perquintill = Perquintill::zero();
+    pub static SharedPoolEngine: bool = false;
+    pub static RandomSeed: u128 = 0;
+    pub static FailCredit: bool = false;
+    pub static MaxTotalEmission: Balance = Balance::MAX;
+    pub static MinClaimInterval: BlockNumber = 0;
+    pub static LargeClaimThreshold: Balance = Balance::MAX;
+    pub static VolumeConversions: u32 = 0;
+    pub const LargeVolumeThreshold: Volume = 1_000_000;
+    pub const MaxSchedules: u32 = 2;
+    pub static ExcludeSelfTrades: bool = true;
+    pub const MaxVirtualAccounts: u32 = 1;
+    pub static EarlyBirdMultiplier: Perquintill = Perquintill::zero();
+    pub static MinClaimable: Balance = 0;
+    pub static ReferralLevels: u32 = 1;
+    pub ReferralLevelShares: Vec<Perquintill> =
+        vec![Perquintill::from_percent(5), Perquintill::from_percent(2)];
+    pub static MaxReferralShare: Perquintill = Perquintill::from_percent(100);
+    pub static InstantShare: Perquintill = Perquintill::one();
+    pub const VestingPeriod: BlockNumber = 100;
+    pub static MaxVolumePerBlock: Volume = Volume::MAX;
+    pub static RoundToUnit: Balance = 0;
+    pub static FundedEmission: bool = false;
+    pub static StrictClaims: bool = false;
+    pub const MaxExportPage: u32 = 2;
+    pub static ActiveBlockWeighting: bool = false;
+    pub static MinDenominator: u128 = 0;
+    pub static ClaimBurn: Perquintill = Perquintill::zero();
+    pub static MaxPrunePerBlock: u32 = 16;
+    pub static MaxIssuance: Balance = Balance::MAX;
+    pub static VolumeRollover: bool = false;
+    pub static RecordShares: bool = false;
+    pub const RewardPalletId: PalletId = PalletId(*b"fuso/rwd");
+}
+
+/// delegates to the token pallet while being able to fail all balance mutations
+pub struct MockAsset;
+
+impl Token<AccountId> for MockAsset {
+    type Balance = Balance;
+    type TokenId = u32;
+
+    fn native_token_id() -> Self::TokenId {
+        TokenModule::native_token_id()
+    }
+
+    fn is_stable(token: &Self::TokenId) -> bool {
+        TokenModule::is_stable(token)
+    }
+
+    fn free_balance(token: &Self::TokenId, who: &AccountId) -> Self::Balance {
+        TokenModule::free_balance(token, who)
+    }
+
+    fn total_issuance(token: &Self::TokenId) -> Self::Balance {
+        TokenModule::total_issuance(token)
+    }
+
+    fn try_mutate_account<R>(
+        token: &Self::TokenId,
+        who: &AccountId,
+        f: impl FnOnce(&mut (Self::Balance, Self::Balance)) -> Result<R, DispatchError>,
+    ) -> Result<R, DispatchError> {
+        if FailCredit::get() {
+            return Err(DispatchError::Other("credit failed"));
+        }
+        TokenModule::try_mutate_account(token, who, f)
+    }
+}
+
+/// returns the seed directly as the leading bytes of the random output
+pub struct SeededRandomness;
+
+impl Randomness<Hash, BlockNumber> for SeededRandomness {
+    fn random(_subject: &[u8]) -> (Hash, BlockNumber) {
+        let mut output = [0u8; 32];
+        output[..16].copy_from_slice(&RandomSeed::get().to_le_bytes());
+        (Hash::from(output), System::block_number())
+    }
+}
+
+/// the `u64` volumes converted exactly to the reward math and back saturating at
+/// `u64::MAX`, counting the conversions in `VolumeConversions`
+pub struct FixedPointVolume;
+
+impl sp_runtime::traits::Convert<Volume, u128> for FixedPointVolume {
+    fn convert(vol: Volume) -> u128 {
+        VolumeConversions::set(VolumeConversions::get().saturating_add(1));
+        vol.into()
+    }
+}
+
+impl sp_runtime::traits::Convert<u128, Volume> for FixedPointVolume {
+    fn convert(vol: u128) -> Volume {
+        vol.min(Volume::MAX.into()) as Volume
+    }
+}
+
+/// only Dave reports volumes through `report_volume`
+pub struct Reporters;
+
+impl frame_support::traits::SortedMembers<AccountId> for Reporters {
+    fn sorted_members() -> Vec<AccountId> {
+        vec![sp_keyring::AccountKeyring::Dave.into()]
+    }
+}
+
+/// every token has a pool of its own id
+pub struct TokenAsPool;
+
+impl sp_runtime::traits::Convert<u32, u8> for TokenAsPool {
+    fn convert(token: u32) -> u8 {
+        token as u8
+    }
+}
+
+/// the accrual model picked by `SharedPoolEngine`, so a test can switch it
+pub struct SwitchedAccrual;
+
+impl Accrual<Test> for SwitchedAccrual {
+    fn record(who: &AccountId, vol: Volume, era: EraId<BlockNumber>) -> DispatchResult {
+        if SharedPoolEngine::get() {
+            <SharedPoolAccrual as Accrual<Test>>::record(who, vol, era)
+        } else {
+            <EraRotationAccrual as Accrual<Test>>::record(who, vol, era)
+        }
+    }
+
+    fn settle(who: &AccountId, era: EraId<BlockNumber>) -> Result<Balance, DispatchError> {
+        if SharedPoolEngine::get() {
+            <SharedPoolAccrual as Accrual<Test>>::settle(who, era)
+        } else {
+            <EraRotationAccrual as Accrual<Test>>::settle(who, era)
+        }
+    }
+
+    fn claimable(who: &AccountId) -> Balance {
+        if SharedPoolEngine::get() {
+            <SharedPoolAccrual as Accrual<Test>>::claimable(who)
+        } else {
+            <EraRotationAccrual as Accrual<Test>>::claimable(who)
+        }
+    }
+
+    fn take(who: &AccountId, max: Balance) -> Result<Balance, DispatchError> {
+        if SharedPoolEngine::get() {
+            <SharedPoolAccrual as Accrual<Test>>::take(who, max)
+        } else {
+            <EraRotationAccrual as Accrual<Test>>::take(who, max)
+        }
+    }
+
+    fn withdraw(
+        who: &AccountId,
+        vol: Volume,
+        era: EraId<BlockNumber>,
+    ) -> Result<Volume, DispatchError> {
+        if SharedPoolEngine::get() {
+            <SharedPoolAccrual as Accrual<Test>>::withdraw(who, vol, era)
+        } else {
+            <EraRotationAccrual as Accrual<Test>>::withdraw(who, vol, era)
+        }
+    }
+
+    fn on_era_closed(era: BlockNumber) -> frame_support::weights::Weight {
+        if SharedPoolEngine::get() {
+            <SharedPoolAccrual as Accrual<Test>>::on_era_closed(era)
+        } else {
+            <EraRotationAccrual as Accrual<Test>>::on_era_closed(era)
+        }
+    }
 }
 
 impl pallet_fuso_reward::Config for Test {
-    type Asset = TokenModule;
+    type AccrualModel = SwitchedAccrual;
+    type ActiveBlockWeighting = ActiveBlockWeighting;
+    type Asset = MockAsset;
+    type BatchSettlement = BatchSettlement;
+    type ClaimBurn = ClaimBurn;
+    type ClaimAsReserved = ClaimAsReserved;
+    type DustLottery = DustLottery;
+    type EarlyBirdMultiplier = EarlyBirdMultiplier;
     type EraDuration = EraDuration;
+    type ErasPerEpoch = ErasPerEpoch;
     type Event = Event;
+    type ExactDistribution = ExactDistribution;
+    type ExcludeNativeTrades = ExcludeNativeTrades;
+    type ExcludeSelfTrades = ExcludeSelfTrades;
+    type FreeClaimWindow = FreeClaimWindow;
+    type FundedEmission = FundedEmission;
+    type HistoryDepth = HistoryDepth;
+    type InstantShare = InstantShare;
+    type KeeperOrigin = frame_system::EnsureRoot<AccountId>;
+    type LargeClaimThreshold = LargeClaimThreshold;
+    type LargeVolumeThreshold = LargeVolumeThreshold;
+    type MaxBatchQuery = MaxBatchQuery;
+    type MaxExportPage = MaxExportPage;
+    type MaxHistoryRange = MaxHistoryRange;
+    type MaxImport = MaxImport;
+    type MaxIssuance = MaxIssuance;
+    type MaxParticipants = MaxParticipants;
+    type MaxPrunePerBlock = MaxPrunePerBlock;
+    type MaxReferralShare = MaxReferralShare;
+    type MaxSchedules = MaxSchedules;
+    type MaxTotalEmission = MaxTotalEmission;
+    type MaxVirtualAccounts = MaxVirtualAccounts;
+    type MaxVolumePerBlock = MaxVolumePerBlock;
+    type MinClaimInterval = MinClaimInterval;
+    type MinClaimable = MinClaimable;
+    type MinDenominator = MinDenominator;
+    type MinEraReward = MinEraReward;
+    type OperatorAccount = OperatorAccount;
+    type OperatorShare = OperatorShare;
+    type PairId = u32;
+    type PalletId = RewardPalletId;
+    type PoolId = u8;
+    type Randomness = SeededRandomness;
+    type RecordShares = RecordShares;
+    type ReferralLevelShares = ReferralLevelShares;
+    type ReferralLevels = ReferralLevels;
+    type ReferralShare = ReferralShare;
+    type RegistrationDeposit = RegistrationDeposit;
     type RewardsPerEra = RewardsPerEra;
+    type RoundToUnit = RoundToUnit;
+    type WeightInfo = ();
+    type StrictClaims = StrictClaims;
+    type StrictTimestamps = StrictTimestamps;
+    type TokenPool = TokenAsPool;
+    type U128ToVolume = FixedPointVolume;
+    type VestingPeriod = VestingPeriod;
+    type Volume = Volume;
+    type VolumeReporter = frame_system::EnsureSignedBy<Reporters, AccountId>;
+    type VolumeRollover = VolumeRollover;
+    type VolumeToU128 = FixedPointVolume;
 }
 
 // Configure a mock runtime to test the pallet.
@@ -114,6 +361,16 @@ frame_support::construct_runtime!(
     }
 );
 
+// Advance to the block `n`, running the hooks of every block passed.
+pub fn run_to_block(n: BlockNumber) {
+    use frame_support::traits::{OnFinalize, OnInitialize};
+    while System::block_number() < n {
+        RewardModule::on_finalize(System::block_number());
+        System::set_block_number(System::block_number() + 1);
+        RewardModule::on_initialize(System::block_number());
+    }
+}
+
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
     system::GenesisConfig::default()
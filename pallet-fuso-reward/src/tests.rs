@@ -1,7 +1,16 @@
 use frame_support::traits::BalanceStatus;
-use frame_support::{assert_noop, assert_ok};
+use frame_support::{
+    assert_noop, assert_ok,
+    traits::{Currency, OnInitialize, OnKilledAccount},
+    weights::Pays,
+};
 use fuso_support::traits::Rewarding;
 use sp_keyring::AccountKeyring;
+use sp_runtime::{
+    traits::{AccountIdConversion, Convert, Saturating},
+    DispatchError, PerThing, Perquintill,
+};
+use sp_std::collections::{btree_map::BTreeMap, btree_set::BTreeSet};
 
 use crate::mock::*;
 use crate::Rewards;
@@ -11,14 +20,15 @@ type RewardModule = Pallet<Test>;
 #[test]
 fn test_reward_should_work() {
     new_test_ext().execute_with(|| {
-        frame_system::Pallet::<Test>::set_block_number(100);
+        // era 100 is only settled once it is closed
+        frame_system::Pallet::<Test>::set_block_number(200);
         let alice: AccountId = AccountKeyring::Alice.into();
         let ferdie: AccountId = AccountKeyring::Ferdie.into();
 
-        Volumes::<Test>::insert(100, 10000);
-        Volumes::<Test>::insert(200, 10000);
-		let vol = RewardModule::volumes(200);
-		assert_eq!(vol, 10000);
+        Volumes::<Test>::insert(RewardModule::era_of(100), 10000);
+        Volumes::<Test>::insert(RewardModule::era_of(200), 10000);
+        let vol = RewardModule::volumes(RewardModule::era_of(200));
+        assert_eq!(vol, 10000);
         Rewards::<Test>::insert(
             &alice,
             Reward {
@@ -47,16 +57,4089 @@ fn test_reward_should_work() {
             }
         );
 
+        // ferdie never trades, so it holds nothing
         let ferdie_reward = RewardModule::rewards(&ferdie);
+        assert_eq!(ferdie_reward, Reward::default());
+        let vol = RewardModule::volumes(RewardModule::era_of(200));
+        assert_eq!(vol, 15000);
+    });
+}
+
+#[test]
+fn test_exact_distribution_should_work() {
+    new_test_ext().execute_with(|| {
+        ExactDistribution::set(true);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 2000, 100));
+        assert_ok!(RewardModule::save_trading(&charlie, 4000, 100));
+        assert_eq!(RewardModule::participants(100), 3);
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 200));
+        assert_ok!(RewardModule::save_trading(&charlie, 1000, 200));
+        let alice_reward = RewardModule::rewards(&alice).confirmed;
+        let bob_reward = RewardModule::rewards(&bob).confirmed;
+        let charlie_reward = RewardModule::rewards(&charlie).confirmed;
+        assert_eq!(
+            alice_reward + bob_reward + charlie_reward,
+            RewardsPerEra::get()
+        );
+        assert!(alice_reward < bob_reward && bob_reward < charlie_reward);
+        assert!(!Allocations::<Test>::contains_key(100, &alice));
+    });
+}
+
+#[test]
+fn test_era_share_should_work() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        frame_system::Pallet::<Test>::set_block_number(150);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        assert_eq!(RewardModule::era_share(&alice, 100), Perquintill::zero());
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let alice_share = RewardModule::era_share(&alice, 100);
+        let bob_share = RewardModule::era_share(&bob, 100);
+        assert_eq!(alice_share, Perquintill::from_percent(25));
+        assert_eq!(alice_share.saturating_add(bob_share), Perquintill::one());
+        assert_eq!(RewardModule::era_share(&alice, 300), Perquintill::zero());
+    });
+}
+
+#[test]
+fn test_zero_rate_should_accrue_nothing() {
+    new_test_ext().execute_with(|| {
+        RewardsPerEra::set(0);
+        frame_system::Pallet::<Test>::set_block_number(300);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 1000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(200)), 1000);
         assert_eq!(
-            ferdie_reward,
+            RewardModule::rewards(&alice),
             Reward {
                 confirmed: 0,
-                pending_vol: 5000,
+                pending_vol: 1000,
                 last_modify: 200
             }
         );
-        let vol = RewardModule::volumes(200);
-        assert_eq!(vol, 15000);
+
+        Rewards::<Test>::insert(
+            &bob,
+            Reward {
+                confirmed: 10 * DOLLARS,
+                pending_vol: 0,
+                last_modify: 100,
+            },
+        );
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(Balances::free_balance(&bob), 10 * DOLLARS);
+        assert!(!Rewards::<Test>::contains_key(&bob));
+
+        RewardModule::on_initialize(300);
+        System::assert_last_event(crate::mock::Event::RewardModule(crate::Event::ZeroRateEra(
+            200,
+        )));
+    });
+}
+
+#[test]
+fn test_claim_as_reserved_should_work() {
+    new_test_ext().execute_with(|| {
+        ClaimAsReserved::set(true);
+        frame_system::Pallet::<Test>::set_block_number(300);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        Rewards::<Test>::insert(
+            &alice,
+            Reward {
+                confirmed: 10 * DOLLARS,
+                pending_vol: 0,
+                last_modify: 100,
+            },
+        );
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::reserved_balance(&alice), 10 * DOLLARS);
+        assert_eq!(Balances::free_balance(&alice), 0);
+    });
+}
+
+#[test]
+fn test_inspect_reward_should_work() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        assert_ok!(RewardModule::inspect_reward(
+            Origin::signed(bob.clone()),
+            alice.clone()
+        ));
+        let reward = RewardModule::rewards(&alice);
+        let projected = RewardModule::projected_reward(&alice);
+        assert_eq!(projected, RewardsPerEra::get() / 4);
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardInspected(
+                alice.clone(),
+                reward.confirmed,
+                reward.pending_vol,
+                reward.last_modify,
+                projected,
+            ),
+        ));
+        assert_eq!(RewardModule::rewards(&alice), reward);
+    });
+}
+
+#[test]
+fn test_era_confirmations_should_not_exceed_pool() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = RewardsPerEra::get();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        // era 100 is still open, its total may grow
+        assert_eq!(RewardModule::rewards(&alice).confirmed, 0);
+        assert_eq!(RewardModule::staged_volumes(&alice, 100), 1000);
+
+        // late volume for an era settled by alice already
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 200));
+
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 300));
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 300));
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            reward / 2 + reward / 4
+        );
+        assert_eq!(
+            RewardModule::rewards(&bob).confirmed,
+            reward / 2 + reward * 3 / 4
+        );
+        assert_eq!(RewardModule::staged_volumes(&alice, 100), 0);
+        assert_eq!(RewardModule::era_distributed(100), reward);
+        assert_eq!(RewardModule::era_distributed(200), reward);
+    });
+}
+
+#[test]
+fn test_claim_should_be_free_after_era_closed() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+
+        frame_system::Pallet::<Test>::set_block_number(201);
+        let post_info = RewardModule::take_reward(Origin::signed(alice.clone())).unwrap();
+        assert_eq!(post_info.pays_fee, Pays::No);
+        assert_eq!(Balances::free_balance(&alice), RewardsPerEra::get());
+
+        // claiming nothing within the window is not free
+        frame_system::Pallet::<Test>::set_block_number(205);
+        let post_info = RewardModule::take_reward(Origin::signed(alice.clone())).unwrap();
+        assert_eq!(post_info.pays_fee, Pays::Yes);
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardClaimed(alice.clone(), 0),
+        ));
+
+        frame_system::Pallet::<Test>::set_block_number(290);
+        let post_info = RewardModule::take_reward(Origin::signed(alice.clone())).unwrap();
+        assert_eq!(post_info.pays_fee, Pays::Yes);
+    });
+}
+
+#[test]
+fn test_referrer_without_trading_should_claim() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        assert_noop!(
+            RewardModule::bind_referrer(Origin::signed(alice.clone()), alice.clone()),
+            Error::<Test>::SelfReferral
+        );
+        assert_ok!(RewardModule::bind_referrer(
+            Origin::signed(alice.clone()),
+            charlie.clone()
+        ));
+        assert_noop!(
+            RewardModule::bind_referrer(Origin::signed(alice.clone()), charlie.clone()),
+            Error::<Test>::ReferrerAlreadyBound
+        );
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        // the cut of the referrer is taken out of the share of the trader
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            RewardsPerEra::get() - RewardsPerEra::get() / 10
+        );
+        assert_eq!(
+            RewardModule::rewards(&charlie),
+            Reward {
+                confirmed: RewardsPerEra::get() / 10,
+                pending_vol: 0,
+                last_modify: 0
+            }
+        );
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 1000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(200)), 1000);
+
+        assert_ok!(RewardModule::take_reward(Origin::signed(charlie.clone())));
+        assert_eq!(Balances::free_balance(&charlie), RewardsPerEra::get() / 10);
+        assert!(!Rewards::<Test>::contains_key(&charlie));
+    });
+}
+
+#[test]
+fn test_future_volume_should_be_rejected_in_strict_mode() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 250));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(200)), 1000);
+
+        StrictTimestamps::set(true);
+        assert_noop!(
+            RewardModule::save_trading(&alice, 1000, 250),
+            Error::<Test>::FutureEra
+        );
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 1000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(200)), 1000);
+    });
+}
+
+#[test]
+fn test_remaining_pool_should_work() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 100));
+        assert_eq!(RewardModule::remaining_pool(100), RewardsPerEra::get());
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(
+            RewardModule::remaining_pool(150),
+            RewardsPerEra::get() - RewardsPerEra::get() / 4
+        );
+        assert_eq!(RewardModule::remaining_pool(200), RewardsPerEra::get());
+
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 200));
+        assert_eq!(RewardModule::remaining_pool(100), 0);
+    });
+}
+
+#[test]
+fn test_failed_claim_should_roll_back() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        Volumes::<Test>::insert(RewardModule::era_of(100), 1000);
+        let reward = Reward {
+            confirmed: 10 * DOLLARS,
+            pending_vol: 1000,
+            last_modify: 100,
+        };
+        Rewards::<Test>::insert(&alice, reward.clone());
+
+        FailCredit::set(true);
+        assert_noop!(
+            RewardModule::take_reward(Origin::signed(alice.clone())),
+            DispatchError::Other("credit failed")
+        );
+        assert_eq!(RewardModule::rewards(&alice), reward);
+        assert_eq!(RewardModule::era_distributed(100), 0);
+
+        FailCredit::set(false);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(
+            Balances::free_balance(&alice),
+            10 * DOLLARS + RewardsPerEra::get()
+        );
+    });
+}
+
+#[test]
+fn test_settled_era_should_reject_late_volume() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&charlie, 1000, 150));
+        assert!(!RewardModule::finalized_eras(100));
+
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert!(RewardModule::finalized_eras(100));
+        assert_noop!(
+            RewardModule::save_trading(&charlie, 1000, 150),
+            Error::<Test>::EraFinalized
+        );
+
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 200));
+        assert_ok!(RewardModule::save_trading(&charlie, 1000, 200));
+        let alice_reward = RewardModule::rewards(&alice).confirmed;
+        assert_eq!(alice_reward, RewardModule::rewards(&bob).confirmed);
+        assert_eq!(alice_reward, RewardModule::rewards(&charlie).confirmed);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 3000);
+    });
+}
+
+#[test]
+fn test_total_claimable_should_work() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(400);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 200));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 300));
+
+        let pool = RewardsPerEra::get();
+        assert_eq!(RewardModule::era_rewards(100, &alice), pool / 2);
+        assert_eq!(RewardModule::era_rewards(200, &alice), pool / 4);
+        assert_eq!(RewardModule::projected_reward(&alice), pool);
+        assert_eq!(
+            RewardModule::total_claimable(&alice),
+            RewardModule::era_rewards(100, &alice)
+                + RewardModule::era_rewards(200, &alice)
+                + RewardModule::projected_reward(&alice)
+        );
+
+        RewardModule::on_initialize(500);
+        assert_eq!(RewardModule::era_rewards(100, &alice), 0);
+        assert_eq!(RewardModule::era_rewards(200, &alice), pool / 4);
+    });
+}
+
+#[test]
+fn test_emission_should_stop_at_cap() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(400);
+        let pool = RewardsPerEra::get();
+        MaxTotalEmission::set(pool + pool / 2);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(RewardModule::rewards(&alice).confirmed, pool);
+        assert_eq!(RewardModule::total_distributed(), pool);
+
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 300));
+        assert_eq!(RewardModule::rewards(&alice).confirmed, pool + pool / 2);
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::EmissionCapReached,
+        ));
+
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 400));
+        assert_eq!(RewardModule::rewards(&alice).confirmed, pool + pool / 2);
+        assert_eq!(RewardModule::total_distributed(), pool + pool / 2);
+        assert_eq!(RewardModule::era_distributed(300), 0);
+    });
+}
+
+#[test]
+fn test_sub_account_volume_should_accrue_to_parent() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let exchange: AccountId = AccountKeyring::Ferdie.into();
+        assert_ok!(RewardModule::set_sub_account_parent(
+            Origin::signed(alice.clone()),
+            Some(exchange.clone())
+        ));
+        assert_ok!(RewardModule::accept_sub_account(
+            Origin::signed(exchange.clone()),
+            alice.clone()
+        ));
+        assert_noop!(
+            RewardModule::set_sub_account_parent(
+                Origin::signed(exchange.clone()),
+                Some(alice.clone())
+            ),
+            Error::<Test>::AttributionLoop
+        );
+        assert_noop!(
+            RewardModule::set_sub_account_parent(Origin::signed(bob.clone()), Some(alice.clone())),
+            Error::<Test>::AttributionLoop
+        );
+
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 1000);
+        assert_eq!(RewardModule::rewards(&exchange).pending_vol, 1000);
+        assert!(!Rewards::<Test>::contains_key(&alice));
+
+        assert_ok!(RewardModule::take_reward(Origin::signed(exchange.clone())));
+        assert_eq!(Balances::free_balance(&exchange), RewardsPerEra::get());
+    });
+}
+
+#[test]
+fn test_sub_account_should_need_the_consent_of_the_parent() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let mallory: AccountId = AccountKeyring::Dave.into();
+        // attaching to bob unasked doesn't keep bob from attaching to alice
+        assert_ok!(RewardModule::set_sub_account_parent(
+            Origin::signed(mallory.clone()),
+            Some(bob.clone())
+        ));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::SubAccountRequested(mallory.clone(), bob.clone()),
+        ));
+        assert_eq!(RewardModule::sub_account_parent(&mallory), None);
+        assert_ok!(RewardModule::save_trading(&mallory, 1000, 200));
+        assert_eq!(RewardModule::rewards(&mallory).pending_vol, 1000);
+        assert_eq!(RewardModule::rewards(&bob).pending_vol, 0);
+        assert_ok!(RewardModule::set_sub_account_parent(
+            Origin::signed(bob.clone()),
+            Some(alice.clone())
+        ));
+        assert_ok!(RewardModule::accept_sub_account(
+            Origin::signed(alice.clone()),
+            bob.clone()
+        ));
+        assert_eq!(RewardModule::sub_account_parent(&bob), Some(alice.clone()));
+
+        // only the requested parent accepts, and a stale request can't loop
+        assert_noop!(
+            RewardModule::accept_sub_account(Origin::signed(alice.clone()), mallory.clone()),
+            Error::<Test>::SubAccountNotRequested
+        );
+        assert_noop!(
+            RewardModule::accept_sub_account(Origin::signed(bob.clone()), mallory.clone()),
+            Error::<Test>::AttributionLoop
+        );
+
+        // the parent drops a sub-account on its own
+        assert_ok!(RewardModule::set_sub_account_parent(
+            Origin::signed(mallory.clone()),
+            Some(alice.clone())
+        ));
+        assert_ok!(RewardModule::accept_sub_account(
+            Origin::signed(alice.clone()),
+            mallory.clone()
+        ));
+        assert_noop!(
+            RewardModule::remove_sub_account(Origin::signed(bob.clone()), mallory.clone()),
+            Error::<Test>::NotSubAccount
+        );
+        assert_ok!(RewardModule::remove_sub_account(
+            Origin::signed(alice.clone()),
+            mallory.clone()
+        ));
+        assert_eq!(RewardModule::sub_account_parent(&mallory), None);
+        assert_eq!(SubAccounts::<Test>::iter_prefix(&alice).count(), 1);
+    });
+}
+
+#[test]
+fn test_era_index_should_work() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_eq!(RewardModule::current_era_index(0), 0);
+        assert_eq!(RewardModule::current_era_index(99), 0);
+        assert_eq!(RewardModule::current_era_index(100), 1);
+        assert_eq!(RewardModule::current_era_index(199), 1);
+        assert_eq!(RewardModule::current_era_index(200), 2);
+
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::VolumeRecorded(alice.clone(), 1000, 1),
+        ));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardConfirmed(alice.clone(), RewardsPerEra::get(), 1),
+        ));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::VolumeRecorded(alice.clone(), 1000, 2),
+        ));
+    });
+}
+
+#[test]
+fn test_holding_should_count_as_volume() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_holding(&alice, 5000, 2, 100));
+        assert_ok!(RewardModule::save_holding(&bob, 100, 100, 100));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 10000);
+        assert_eq!(RewardModule::rewards(&bob).pending_vol, 10000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 20000);
+        assert_noop!(
+            RewardModule::save_holding(&alice, u64::MAX, 2, 100),
+            Error::<Test>::Overflow
+        );
+
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(Balances::free_balance(&alice), RewardsPerEra::get() / 2);
+        assert_eq!(Balances::free_balance(&bob), RewardsPerEra::get() / 2);
+    });
+}
+
+#[test]
+fn test_simulate_era_should_match_confirmations() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 2000, 100));
+        assert_ok!(RewardModule::save_trading(&charlie, 4000, 100));
+        assert!(RewardModule::simulate_era(200, 0, 10).is_empty());
+
+        let simulated = RewardModule::simulate_era(100, 0, 10);
+        assert_eq!(simulated.len(), 3);
+        let mut paged = RewardModule::simulate_era(100, 0, 2);
+        paged.append(&mut RewardModule::simulate_era(100, 2, 2));
+        assert_eq!(paged, simulated);
+
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 200));
+        assert_ok!(RewardModule::save_trading(&charlie, 1000, 200));
+        let mut total = 0;
+        for (who, amount) in simulated {
+            assert_eq!(RewardModule::era_rewards(100, &who), amount);
+            total += amount;
+        }
+        assert_eq!(total, RewardModule::era_distributed(100));
+    });
+}
+
+#[test]
+fn test_revoked_volume_should_reduce_era_total() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+
+        assert_ok!(RewardModule::revoke_trading(&alice, 400, 150));
+        assert_ok!(RewardModule::slash_volume(
+            Origin::root(),
+            bob.clone(),
+            5000
+        ));
+        assert_noop!(
+            RewardModule::slash_volume(Origin::signed(alice.clone()), bob.clone(), 1),
+            sp_runtime::traits::BadOrigin
+        );
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 600);
+        assert_eq!(RewardModule::rewards(&bob).pending_vol, 0);
+        assert_eq!(RewardModule::participants(100), 1);
+        let pending: u64 = Rewards::<Test>::iter()
+            .filter(|(_, r)| r.last_modify == 100)
+            .map(|(_, r)| r.pending_vol)
+            .sum();
+        assert_eq!(pending, RewardModule::volumes(RewardModule::era_of(100)));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), RewardsPerEra::get());
+    });
+}
+
+#[test]
+fn test_epoch_distributed_should_work() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(400);
+        assert_eq!(RewardModule::epoch_of(0), 0);
+        assert_eq!(RewardModule::epoch_of(199), 0);
+        assert_eq!(RewardModule::epoch_of(200), 1);
+        assert_eq!(RewardModule::epoch_of(399), 1);
+        assert_eq!(RewardModule::epoch_of(400), 2);
+
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let pool = RewardsPerEra::get();
+        for era in [100, 200, 300, 400] {
+            assert_ok!(RewardModule::save_trading(&alice, 1000, era));
+        }
+        assert_eq!(RewardModule::epoch_distributed(0), pool);
+        assert_eq!(RewardModule::epoch_distributed(1), 2 * pool);
+        assert_eq!(RewardModule::epoch_distributed(2), 0);
+    });
+}
+
+#[test]
+fn test_large_claim_should_bypass_cooldown() {
+    new_test_ext().execute_with(|| {
+        MinClaimInterval::set(50);
+        LargeClaimThreshold::set(100 * DOLLARS);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let confirmed = |amount| Reward {
+            confirmed: amount,
+            pending_vol: 0,
+            last_modify: 100,
+        };
+        frame_system::Pallet::<Test>::set_block_number(300);
+        Rewards::<Test>::insert(&alice, confirmed(10 * DOLLARS));
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+
+        frame_system::Pallet::<Test>::set_block_number(310);
+        Rewards::<Test>::insert(&alice, confirmed(10 * DOLLARS));
+        assert_noop!(
+            RewardModule::take_reward(Origin::signed(alice.clone())),
+            Error::<Test>::ClaimTooFrequent
+        );
+
+        Rewards::<Test>::insert(&alice, confirmed(1000 * DOLLARS));
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), 1010 * DOLLARS);
+
+        frame_system::Pallet::<Test>::set_block_number(360);
+        Rewards::<Test>::insert(&alice, confirmed(10 * DOLLARS));
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+    });
+}
+
+#[test]
+fn test_era_closed_summary_should_work() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 160));
+        assert_ok!(RewardModule::save_trading(&alice, 500, 170));
+
+        assert_eq!(RewardModule::on_initialize(199), 0);
+        frame_system::Pallet::<Test>::set_block_number(200);
+        RewardModule::on_initialize(200);
+        System::assert_last_event(crate::mock::Event::RewardModule(crate::Event::EraClosed(
+            100,
+            4500,
+            2,
+            RewardsPerEra::get(),
+        )));
+
+        frame_system::Pallet::<Test>::set_block_number(300);
+        RewardModule::on_initialize(300);
+        System::assert_last_event(crate::mock::Event::RewardModule(crate::Event::EraClosed(
+            200, 0, 0, 0,
+        )));
+    });
+}
+
+#[test]
+fn test_first_trade_in_late_era_should_not_settle_era_zero() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(1050);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(0)), 0);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 1050));
+        assert_eq!(
+            RewardModule::rewards(&alice),
+            Reward {
+                confirmed: 0,
+                pending_vol: 1000,
+                last_modify: 1000
+            }
+        );
+        assert!(!System::events().iter().any(|record| matches!(
+            record.event,
+            crate::mock::Event::RewardModule(crate::Event::RewardConfirmed(..))
+        )));
+        assert!(!RewardModule::finalized_eras(0));
+        assert_eq!(RewardModule::era_distributed(0), 0);
+    });
+}
+
+#[test]
+fn test_registration_should_keep_first_claim_alive() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(1050);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_noop!(
+            RewardModule::register_for_rewards(Origin::signed(alice.clone())),
+            Error::<Test>::InsufficientBalance
+        );
+        Balances::make_free_balance_be(&alice, 5 * DOLLARS);
+        assert_ok!(RewardModule::register_for_rewards(Origin::signed(
+            alice.clone()
+        )));
+        assert_noop!(
+            RewardModule::register_for_rewards(Origin::signed(alice.clone())),
+            Error::<Test>::AlreadyRegistered
+        );
+        assert_eq!(RewardModule::registrations(&alice), Some(DOLLARS));
+        assert_eq!(Balances::free_balance(&alice), 4 * DOLLARS);
+        assert_eq!(Balances::reserved_balance(&alice), DOLLARS);
+
+        // spending all the free balance doesn't reap the registered account
+        Balances::make_free_balance_be(&alice, 0);
+        assert_eq!(Balances::total_balance(&alice), DOLLARS);
+
+        let dust = 1000;
+        let reward = Reward {
+            confirmed: dust,
+            pending_vol: 0,
+            last_modify: 1000,
+        };
+        Rewards::<Test>::insert(&alice, reward.clone());
+        Rewards::<Test>::insert(&bob, reward);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), DOLLARS + dust);
+        assert_eq!(Balances::reserved_balance(&alice), 0);
+        assert_eq!(RewardModule::registrations(&alice), None);
+
+        // the unregistered account receives less than the existential deposit
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(Balances::total_balance(&bob), 0);
+    });
+}
+
+#[test]
+fn test_era_should_close_at_first_block_of_next_era() {
+    new_test_ext().execute_with(|| {
+        assert!(!RewardModule::is_era_closed(100, 100));
+        assert!(!RewardModule::is_era_closed(100, 199));
+        assert!(RewardModule::is_era_closed(100, 200));
+        assert!(RewardModule::is_era_closed(150, 200));
+        assert!(RewardModule::is_era_closed(100, 250));
+
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        frame_system::Pallet::<Test>::set_block_number(199);
+        assert_eq!(RewardModule::era_share(&alice, 100), Perquintill::zero());
+        assert!(RewardModule::simulate_era(100, 0, 10).is_empty());
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_eq!(RewardModule::era_share(&alice, 100), Perquintill::one());
+        assert_eq!(
+            RewardModule::simulate_era(100, 0, 10),
+            vec![(alice, RewardsPerEra::get())]
+        );
+    });
+}
+
+#[test]
+fn test_save_trading_weight_should_reflect_rotation() {
+    use crate::weights::WeightInfo;
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let same_era = <() as WeightInfo>::save_trading_same_era();
+        let rotate = <() as WeightInfo>::save_trading_rotate();
+        assert!(same_era < rotate);
+        assert_eq!(RewardModule::save_trading_weight(&alice, 100), same_era);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_eq!(RewardModule::save_trading_weight(&alice, 150), same_era);
+        assert_eq!(RewardModule::save_trading_weight(&alice, 200), rotate);
+    });
+}
+
+#[test]
+fn test_pools_should_accrue_independently() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let (spot, perp) = (1u8, 2u8);
+        assert_noop!(
+            RewardModule::set_pool_rewards_per_era(Origin::signed(alice.clone()), spot, 1),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(RewardModule::set_pool_rewards_per_era(
+            Origin::root(),
+            spot,
+            4 * DOLLARS
+        ));
+        assert_ok!(RewardModule::set_pool_rewards_per_era(
+            Origin::root(),
+            perp,
+            10 * DOLLARS
+        ));
+        assert_ok!(RewardModule::save_pool_trading(spot, &alice, 1000, 100));
+        assert_ok!(RewardModule::save_pool_trading(spot, &bob, 3000, 150));
+        assert_ok!(RewardModule::save_pool_trading(perp, &alice, 500, 120));
+        assert_eq!(RewardModule::pool_volumes(spot, 100), 4000);
+        assert_eq!(RewardModule::pool_volumes(perp, 100), 500);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 0);
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_pool_reward(
+            Origin::signed(alice.clone()),
+            spot
+        ));
+        assert_eq!(Balances::free_balance(&alice), DOLLARS);
+        assert_eq!(
+            RewardModule::pool_rewards(perp, &alice),
+            Reward {
+                confirmed: 0,
+                pending_vol: 500,
+                last_modify: 100
+            }
+        );
+        assert_ok!(RewardModule::take_pool_reward(
+            Origin::signed(alice.clone()),
+            perp
+        ));
+        assert_eq!(Balances::free_balance(&alice), 11 * DOLLARS);
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::PoolRewardClaimed(perp, alice.clone(), 10 * DOLLARS),
+        ));
+
+        assert_ok!(RewardModule::take_pool_reward(
+            Origin::signed(bob.clone()),
+            spot
+        ));
+        assert_eq!(Balances::free_balance(&bob), 3 * DOLLARS);
+        assert_ok!(RewardModule::take_pool_reward(
+            Origin::signed(bob.clone()),
+            perp
+        ));
+        assert_eq!(Balances::free_balance(&bob), 3 * DOLLARS);
+        assert_eq!(RewardModule::rewards(&alice), Default::default());
+    });
+}
+
+#[test]
+fn test_pool_accrual_should_stage_and_finalize_eras() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let spot = 1u8;
+        assert_ok!(RewardModule::set_pool_rewards_per_era(
+            Origin::root(),
+            spot,
+            4 * DOLLARS
+        ));
+        StrictTimestamps::set(true);
+        assert_noop!(
+            RewardModule::save_pool_trading(spot, &alice, 1000, 250),
+            Error::<Test>::FutureEra
+        );
+        StrictTimestamps::set(false);
+
+        // the open era is staged until it closes
+        assert_ok!(RewardModule::save_pool_trading(spot, &alice, 1000, 150));
+        assert_ok!(RewardModule::save_pool_trading(spot, &alice, 1000, 250));
+        assert_eq!(
+            RewardModule::pool_staged_volumes((spot, alice.clone()), 100),
+            1000
+        );
+        assert_eq!(RewardModule::pool_rewards(spot, &alice).confirmed, 0);
+        assert_ok!(RewardModule::save_pool_trading(spot, &bob, 3000, 160));
+        assert_ok!(RewardModule::save_pair_trading(Some(1), &bob, 10, 160));
+
+        // the rate is frozen when the era closes
+        frame_system::Pallet::<Test>::set_block_number(200);
+        RewardModule::on_initialize(200);
+        assert_ok!(RewardModule::set_pool_rewards_per_era(
+            Origin::root(),
+            spot,
+            40 * DOLLARS
+        ));
+        assert_eq!(RewardModule::pool_rate_of(spot, 100), 4 * DOLLARS);
+        assert_ok!(RewardModule::take_pool_reward(
+            Origin::signed(alice.clone()),
+            spot
+        ));
+        assert_eq!(Balances::free_balance(&alice), DOLLARS);
+        assert_eq!(
+            RewardModule::pool_staged_volumes((spot, alice.clone()), 100),
+            0
+        );
+        assert_eq!(RewardModule::pool_rewards(spot, &alice).pending_vol, 1000);
+        assert!(RewardModule::pool_finalized_eras(100, spot));
+        assert_noop!(
+            RewardModule::save_pool_trading(spot, &bob, 1, 199),
+            Error::<Test>::EraFinalized
+        );
+
+        // the pool and pair totals are pruned along with the era
+        RewardModule::on_initialize(500);
+        assert_eq!(RewardModule::pool_volumes(spot, 100), 0);
+        assert_eq!(RewardModule::pair_volumes(1, 100), 0);
+        assert_eq!(RewardModule::pool_era_rates(100, spot), None);
+        assert!(!RewardModule::pool_finalized_eras(100, spot));
+        assert_noop!(
+            RewardModule::save_pool_trading(spot, &bob, 1, 150),
+            Error::<Test>::EraFinalized
+        );
+    });
+}
+
+#[test]
+fn test_claim_rate_should_work() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_eq!(RewardModule::claim_rate(), Perquintill::zero());
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&bob, 1, 200));
+        assert_eq!(RewardModule::claim_rate(), Perquintill::zero());
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(RewardModule::total_distributed(), RewardsPerEra::get());
+        assert_eq!(RewardModule::total_claimed(), RewardsPerEra::get() / 4);
+        assert_eq!(RewardModule::claim_rate(), Perquintill::from_percent(25));
+    });
+}
+
+#[test]
+fn test_native_trades_can_be_excluded() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_native_trading(&alice, 1000, 100));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 1000);
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+
+        ExcludeNativeTrades::set(true);
+        assert_ok!(RewardModule::save_native_trading(&alice, 1000, 100));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 1000);
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 2000);
+        ExcludeNativeTrades::set(false);
+    });
+}
+
+#[test]
+fn test_import_volume_snapshot_should_work() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        assert_noop!(
+            RewardModule::import_volume_snapshot(
+                Origin::root(),
+                vec![(alice.clone(), 1000, 150)].try_into().unwrap()
+            ),
+            Error::<Test>::MisalignedEra
+        );
+        assert_noop!(
+            RewardModule::import_volume_snapshot(
+                Origin::root(),
+                vec![(alice.clone(), 1000, 100), (alice.clone(), 1000, 200)]
+                    .try_into()
+                    .unwrap()
+            ),
+            Error::<Test>::DuplicateEntry
+        );
+        assert_ok!(RewardModule::import_volume_snapshot(
+            Origin::root(),
+            vec![
+                (alice.clone(), 1000, 100),
+                (bob.clone(), 3000, 100),
+                (charlie.clone(), 500, 200)
+            ]
+            .try_into()
+            .unwrap()
+        ));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 4000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(200)), 500);
+        assert_eq!(RewardModule::participants(100), 2);
+        assert_eq!(RewardModule::era_volumes(100, &bob), 3000);
+        assert_noop!(
+            RewardModule::import_volume_snapshot(
+                Origin::root(),
+                vec![(bob.clone(), 1000, 300)].try_into().unwrap()
+            ),
+            Error::<Test>::DuplicateEntry
+        );
+
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_ok!(RewardModule::save_trading(&alice, 200, 300));
+        assert_eq!(
+            RewardModule::rewards(&alice),
+            Reward {
+                confirmed: RewardsPerEra::get() / 4,
+                pending_vol: 200,
+                last_modify: 300
+            }
+        );
+        assert_ok!(RewardModule::take_reward(Origin::signed(charlie.clone())));
+        assert_eq!(Balances::free_balance(&charlie), RewardsPerEra::get());
+
+        // the settled eras take no more volume
+        let dave: AccountId = AccountKeyring::Dave.into();
+        assert_noop!(
+            RewardModule::import_volume_snapshot(
+                Origin::root(),
+                vec![(dave.clone(), 1000, 100)].try_into().unwrap()
+            ),
+            Error::<Test>::EraFinalized
+        );
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 4000);
+    });
+}
+
+#[test]
+fn test_dust_lottery_should_be_weighted_and_deterministic() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        ExactDistribution::set(true);
+        assert_ok!(RewardModule::save_trading(&alice, 1, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 2, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let reward = RewardsPerEra::get();
+        let share_of = |who: &AccountId| {
+            RewardModule::simulate_era(100, 0, 10)
+                .into_iter()
+                .find(|(w, _)| w == who)
+                .map(|(_, r)| r)
+                .unwrap()
+        };
+        // the largest remainder gets the dust without the lottery
+        assert_eq!(share_of(&bob), reward * 2 / 3 + 1);
+
+        DustLottery::set(true);
+        let mut alice_wins = 0;
+        for seed in 0..3 {
+            RandomSeed::set(seed);
+            let alice_share = share_of(&alice);
+            assert_eq!(alice_share, share_of(&alice));
+            assert_eq!(alice_share + share_of(&bob), reward);
+            if alice_share == reward / 3 + 1 {
+                alice_wins += 1;
+            }
+        }
+        assert_eq!(alice_wins, 1);
+
+        RandomSeed::set(7);
+        let expected = share_of(&alice);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), expected);
+    });
+}
+
+#[test]
+fn test_dust_lottery_should_only_draw_rounding_dust() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        ExactDistribution::set(true);
+        DustLottery::set(true);
+        assert_ok!(RewardModule::save_trading(&alice, 1, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 2, 100));
+        // the volume of the era attributed to nobody isn't dust
+        Volumes::<Test>::insert(RewardModule::era_of(100), 30);
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let reward = RewardsPerEra::get();
+        for seed in 0..3 {
+            RandomSeed::set(seed);
+            let shares = RewardModule::simulate_era(100, 0, 10);
+            let total = shares.iter().fold(0, |acc, (_, r)| acc + r);
+            assert_eq!(total, reward / 10);
+            for (who, r) in shares {
+                let vol = if who == alice { 1 } else { 2 };
+                assert!(r <= reward * vol / 30 + 1);
+            }
+        }
+        DustLottery::set(false);
+        ExactDistribution::set(false);
+    });
+}
+
+#[test]
+fn test_kyc_hold_should_block_claims_but_not_accrual() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_noop!(
+            RewardModule::set_kyc_hold(Origin::signed(alice.clone()), alice.clone()),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(RewardModule::set_kyc_hold(Origin::root(), alice.clone()));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            RewardsPerEra::get()
+        );
+        assert_noop!(
+            RewardModule::take_reward(Origin::signed(alice.clone())),
+            Error::<Test>::KycRequired
+        );
+
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_ok!(RewardModule::clear_kyc_hold(Origin::root(), alice.clone()));
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), 2 * RewardsPerEra::get());
+    });
+}
+
+#[test]
+fn test_next_era_emission_should_reflect_emission_cap() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let reward = RewardsPerEra::get();
+        MaxTotalEmission::set(reward + reward / 2);
+        assert_eq!(RewardModule::next_era_emission(150), reward);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(RewardModule::next_era_emission(250), reward / 2);
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(RewardModule::next_era_emission(300), 0);
+    });
+}
+
+#[test]
+fn test_batch_settlement_should_match_immediate_application() {
+    use frame_support::traits::OnFinalize;
+    let alice: AccountId = AccountKeyring::Alice.into();
+    let bob: AccountId = AccountKeyring::Bob.into();
+    let charlie: AccountId = AccountKeyring::Charlie.into();
+    let trades = vec![
+        (alice.clone(), 100, 200),
+        (bob.clone(), 300, 200),
+        (alice.clone(), 200, 200),
+        (charlie.clone(), 50, 250),
+        (bob.clone(), 100, 200),
+        (alice.clone(), 700, 250),
+    ];
+    let run = |batch: bool| {
+        new_test_ext().execute_with(|| {
+            BatchSettlement::set(batch);
+            assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+            assert_ok!(RewardModule::save_trading(&bob, 3000, 100));
+            RewardModule::on_finalize(100);
+            frame_system::Pallet::<Test>::set_block_number(250);
+            for (who, vol, at) in trades.iter() {
+                assert_ok!(RewardModule::save_trading(who, *vol, *at));
+            }
+            if batch {
+                assert_eq!(RewardModule::trade_buffer().len(), trades.len());
+                assert_eq!(RewardModule::volumes(RewardModule::era_of(200)), 0);
+            }
+            RewardModule::on_finalize(250);
+            assert!(!TradeBuffer::<Test>::exists());
+            (
+                RewardModule::volumes(RewardModule::era_of(200)),
+                RewardModule::participants(200),
+                [&alice, &bob, &charlie]
+                    .iter()
+                    .map(|who| {
+                        (
+                            RewardModule::rewards(who),
+                            RewardModule::era_volumes(200, who),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+    };
+    assert_eq!(run(true), run(false));
+}
+
+#[test]
+fn test_batch_settlement_should_boost_the_first_trade_like_immediate_application() {
+    use frame_support::traits::OnFinalize;
+    let alice: AccountId = AccountKeyring::Alice.into();
+    let bob: AccountId = AccountKeyring::Bob.into();
+    let charlie: AccountId = AccountKeyring::Charlie.into();
+    let run = |batch: bool| {
+        new_test_ext().execute_with(|| {
+            BatchSettlement::set(batch);
+            EarlyBirdMultiplier::set(Perquintill::from_percent(50));
+            assert_ok!(RewardModule::set_virtual_volume(
+                Origin::root(),
+                charlie.clone(),
+                1000
+            ));
+            frame_system::Pallet::<Test>::set_block_number(100);
+            RewardModule::on_initialize(100);
+            assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+            assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+            assert_ok!(RewardModule::save_trading(&bob, 1000, 150));
+            assert_ok!(RewardModule::save_trading(&charlie, 1000, 150));
+            RewardModule::on_finalize(100);
+            EarlyBirdMultiplier::set(Perquintill::zero());
+            BatchSettlement::set(false);
+            [&alice, &bob, &charlie]
+                .iter()
+                .map(|who| RewardModule::rewards(who).pending_vol)
+                .collect::<Vec<_>>()
+        })
+    };
+    // the virtual volume of charlie takes the boost of no trade
+    assert_eq!(run(false), vec![2500, 1500, 2000]);
+    assert_eq!(run(true), run(false));
+}
+
+#[test]
+fn test_era_reward_should_be_floored() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        MinEraReward::set(DOLLARS);
+        assert_eq!(RewardModule::era_reward_at(100), RewardsPerEra::get());
+        // a rate decayed down to nothing still pays the floor
+        RewardsPerEra::set(1);
+        assert_eq!(RewardModule::era_reward_at(100), DOLLARS);
+        RewardsPerEra::set(0);
+        assert_eq!(RewardModule::era_reward_at(100), DOLLARS);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), DOLLARS);
+    });
+}
+
+#[test]
+fn test_pending_rewards_batch_should_match_single_queries() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        let dave: AccountId = AccountKeyring::Dave.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&charlie, 500, 200));
+        let accounts = vec![alice.clone(), bob.clone(), charlie.clone(), dave.clone()];
+        let batch = RewardModule::pending_rewards_batch(accounts.clone());
+        assert_eq!(batch.len(), MaxBatchQuery::get() as usize);
+        for (who, reward) in accounts.iter().zip(batch.iter()) {
+            assert_eq!(*reward, RewardModule::total_claimable(who));
+        }
+        assert_eq!(
+            batch,
+            vec![RewardsPerEra::get() / 4, RewardsPerEra::get() * 3 / 4, 0]
+        );
+    });
+}
+
+#[test]
+fn test_claim_should_not_be_affected_by_front_running_volume() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 100));
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        let expected = RewardModule::rewards(&alice).confirmed;
+        assert_eq!(expected, RewardsPerEra::get() / 4);
+
+        // the front-runner settles the closed era, then volume is pushed before the claim
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_noop!(
+            RewardModule::save_trading(&charlie, 1_000_000, 100),
+            Error::<Test>::EraFinalized
+        );
+        assert_ok!(RewardModule::save_trading(&charlie, 1_000_000, 200));
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), expected);
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+        assert_eq!(Balances::free_balance(&bob), RewardsPerEra::get() * 3 / 4);
+    });
+}
+
+#[test]
+fn test_donation_should_increase_era_payouts() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let ferdie: AccountId = AccountKeyring::Ferdie.into();
+        let reward = RewardsPerEra::get();
+        Balances::make_free_balance_be(&ferdie, 10 * reward);
+        frame_system::Pallet::<Test>::set_block_number(150);
+        assert_noop!(
+            RewardModule::donate_to_era(Origin::signed(ferdie.clone()), 0, reward),
+            Error::<Test>::EraAlreadyClosed
+        );
+        assert_ok!(RewardModule::donate_to_era(
+            Origin::signed(ferdie.clone()),
+            250,
+            4 * reward
+        ));
+        assert_eq!(Balances::free_balance(&ferdie), 6 * reward);
+        assert_eq!(
+            Balances::free_balance(&RewardModule::pool_account()),
+            4 * reward
+        );
+        assert_eq!(RewardModule::pool_balance(), 4 * reward);
+        assert_eq!(RewardModule::era_bonus_pool(200), 4 * reward);
+        assert_eq!(RewardModule::era_reward_at(200), 5 * reward);
+
+        for era in [100, 200] {
+            assert_ok!(RewardModule::save_trading(&alice, 1000, era));
+            assert_ok!(RewardModule::save_trading(&bob, 3000, era));
+        }
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 300));
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            reward / 4 + 5 * reward / 4
+        );
+        assert_noop!(
+            RewardModule::donate_to_era(Origin::signed(ferdie.clone()), 200, reward),
+            Error::<Test>::EraAlreadyClosed
+        );
+    });
+}
+
+#[test]
+fn test_era_of_should_align_to_era_start() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_eq!(RewardModule::era_of(100), RewardModule::era_of(199));
+        assert_eq!(RewardModule::era_of(199).start(), 100);
+        assert!(RewardModule::era_of(200) > RewardModule::era_of(199));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(120)), 1000);
+        assert_eq!(RewardModule::total_volume(199), 1000);
+    });
+}
+
+#[test]
+fn test_rewards_should_survive_era_duration_change() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = RewardsPerEra::get();
+        frame_system::Pallet::<Test>::set_block_number(150);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        assert_noop!(
+            RewardModule::set_era_duration(Origin::root(), 0),
+            Error::<Test>::InvalidEraDuration
+        );
+        assert_ok!(RewardModule::set_era_duration(Origin::root(), 50));
+        assert_ok!(RewardModule::set_era_duration(Origin::root(), 30));
+        assert_eq!(RewardModule::era_alignments(), vec![(200, 30)]);
+
+        // the current era keeps its alignment until the next boundary
+        assert_eq!(RewardModule::era_duration(), 100);
+        assert_eq!(RewardModule::era_of(160).start(), 100);
+        frame_system::Pallet::<Test>::set_block_number(165);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 165));
+        assert_eq!(RewardModule::rewards(&alice).confirmed, 0);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(165)), 5000);
+        assert_eq!(RewardModule::current_era_index(199), 1);
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_eq!(RewardModule::era_duration(), 30);
+        assert_eq!(RewardModule::era_of(199).start(), 100);
+        assert_eq!(RewardModule::era_of(229).start(), 200);
+        assert_eq!(RewardModule::era_of(230).start(), 230);
+        assert_eq!(RewardModule::current_era_index(230), 3);
+        assert!(RewardModule::is_era_closed(100, 200));
+        assert!(!RewardModule::is_era_closed(200, 229));
+        assert_ok!(RewardModule::save_trading(&alice, 500, 205));
+        assert_eq!(RewardModule::rewards(&alice).confirmed, reward * 2 / 5);
+
+        frame_system::Pallet::<Test>::set_block_number(230);
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(Balances::free_balance(&bob), reward * 3 / 5);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), reward * 2 / 5 + reward);
+    });
+}
+
+#[test]
+fn test_emit_claim_history_should_work() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = RewardsPerEra::get();
+        frame_system::Pallet::<Test>::set_block_number(450);
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 200));
+        for era in [100, 200, 300, 400] {
+            assert_ok!(RewardModule::save_trading(&alice, 1000, era));
+        }
+        assert_noop!(
+            RewardModule::emit_claim_history(Origin::signed(bob.clone()), alice.clone(), 0, 200),
+            Error::<Test>::EraNotRetained
+        );
+        assert_noop!(
+            RewardModule::emit_claim_history(Origin::signed(bob.clone()), alice.clone(), 100, 400),
+            Error::<Test>::RangeTooLong
+        );
+        System::reset_events();
+        assert_ok!(RewardModule::emit_claim_history(
+            Origin::signed(bob.clone()),
+            alice.clone(),
+            150,
+            399
+        ));
+        let entries: Vec<_> = System::events()
+            .into_iter()
+            .map(|record| record.event)
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                crate::mock::Event::RewardModule(crate::Event::EraRewardEntry(
+                    alice.clone(),
+                    100,
+                    reward
+                )),
+                crate::mock::Event::RewardModule(crate::Event::EraRewardEntry(
+                    alice.clone(),
+                    200,
+                    reward / 2
+                )),
+                crate::mock::Event::RewardModule(crate::Event::EraRewardEntry(
+                    alice.clone(),
+                    300,
+                    reward
+                )),
+            ]
+        );
+    });
+}
+
+#[test]
+fn test_operator_share_should_be_credited_once_per_era() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let operator: AccountId = AccountKeyring::Eve.into();
+        let reward = RewardsPerEra::get();
+        OperatorShare::set(Perquintill::from_percent(20));
+        assert_eq!(RewardModule::traders_pool(100), reward * 4 / 5);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 100));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        RewardModule::on_initialize(200);
+        assert_eq!(RewardModule::rewards(&operator).confirmed, reward / 5);
+        assert!(RewardModule::operator_paid(100));
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::OperatorCredited(operator.clone(), 100, reward / 5),
+        ));
+        RewardModule::on_initialize(200);
+        assert_eq!(RewardModule::rewards(&operator).confirmed, reward / 5);
+
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(Balances::free_balance(&alice), reward / 5);
+        assert_eq!(Balances::free_balance(&bob), reward * 3 / 5);
+        assert_ok!(RewardModule::take_reward(Origin::signed(operator.clone())));
+        assert_eq!(Balances::free_balance(&operator), reward / 5);
+        assert_eq!(RewardModule::total_distributed(), reward);
+
+        // nothing is credited for an era without volume
+        frame_system::Pallet::<Test>::set_block_number(300);
+        RewardModule::on_initialize(300);
+        assert!(!RewardModule::operator_paid(200));
+    });
+}
+
+#[test]
+fn test_share_of_huge_pool_should_be_exact() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = u128::MAX - 7;
+        RewardsPerEra::set(reward);
+        assert_ok!(RewardModule::save_trading(&alice, 1, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 999_999, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&alice, 1, 200));
+        assert_ok!(RewardModule::save_trading(&bob, 1, 200));
+        assert_eq!(RewardModule::rewards(&alice).confirmed, reward / 1_000_000);
+        assert_eq!(
+            RewardModule::rewards(&bob).confirmed,
+            Perquintill::from_rational(999_999u128, 1_000_000u128).mul_floor(reward)
+        );
+        assert!(RewardModule::era_distributed(100) <= reward);
+    });
+}
+
+#[test]
+fn test_last_trade_should_be_tracked() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_eq!(RewardModule::last_trade_era(&alice), None);
+        assert_eq!(RewardModule::last_trade_block(&alice), None);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_eq!(RewardModule::last_trade_era(&alice), Some(100));
+        assert_eq!(RewardModule::last_trade_block(&alice), Some(150));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 320));
+        assert_eq!(RewardModule::last_trade_era(&alice), Some(300));
+        assert_eq!(RewardModule::last_trade_block(&alice), Some(320));
+
+        // claiming rotates `last_modify` but isn't a trade
+        frame_system::Pallet::<Test>::set_block_number(550);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(RewardModule::rewards(&alice).last_modify, 0);
+        assert_eq!(RewardModule::last_trade_era(&alice), Some(300));
+    });
+}
+
+#[test]
+fn test_shared_pool_engine_should_match_masterchef() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = RewardsPerEra::get();
+        SharedPoolEngine::set(true);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 0);
+        assert_eq!(RewardModule::total_shares(), 1000);
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        RewardModule::on_initialize(200);
+        let acc = reward * ACC_REWARD_PRECISION / 1000;
+        assert_eq!(RewardModule::acc_reward_per_share(), acc);
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 200));
+        assert_eq!(
+            RewardModule::shares(&bob).reward_debt,
+            3000 * acc / ACC_REWARD_PRECISION
+        );
+
+        // alice claims anytime without waiting for her shares to rotate
+        assert_eq!(RewardModule::total_claimable(&alice), reward);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), reward);
+
+        frame_system::Pallet::<Test>::set_block_number(300);
+        RewardModule::on_initialize(300);
+        let acc = acc + reward * ACC_REWARD_PRECISION / 4000;
+        assert_eq!(RewardModule::acc_reward_per_share(), acc);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(
+            Balances::free_balance(&alice),
+            1000 * acc / ACC_REWARD_PRECISION
+        );
+        assert_eq!(
+            Balances::free_balance(&bob),
+            3000 * acc / ACC_REWARD_PRECISION
+                - 3000 * (reward * ACC_REWARD_PRECISION / 1000) / ACC_REWARD_PRECISION
+        );
+        assert_eq!(Balances::free_balance(&bob), reward * 3 / 4);
+        assert_eq!(RewardModule::total_distributed(), 2 * reward);
+    });
+}
+
+#[test]
+fn test_claim_in_trading_block_should_not_pay_open_era() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        frame_system::Pallet::<Test>::set_block_number(150);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardClaimed(alice.clone(), 0),
+        ));
+        assert_eq!(
+            RewardModule::rewards(&alice),
+            Reward {
+                confirmed: 0,
+                pending_vol: 1000,
+                last_modify: 100
+            }
+        );
+
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 250));
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), RewardsPerEra::get());
+        assert_eq!(
+            RewardModule::rewards(&alice),
+            Reward {
+                confirmed: 0,
+                pending_vol: 1000,
+                last_modify: 200
+            }
+        );
+    });
+}
+
+#[test]
+fn test_volume_history_should_match_volumes() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        frame_system::Pallet::<Test>::set_block_number(450);
+        for (vol, era) in [(1000, 100), (0, 200), (3000, 300), (500, 400)] {
+            assert_ok!(RewardModule::save_trading(&alice, vol, era));
+        }
+        let history = RewardModule::volume_history(100, 399).unwrap();
+        assert_eq!(history, vec![(100, 1000), (200, 0), (300, 3000)]);
+        for (era, vol) in history {
+            assert_eq!(RewardModule::volumes(RewardModule::era_of(era)), vol);
+        }
+        assert_eq!(
+            RewardModule::volume_history(300, 450).unwrap(),
+            vec![(300, 3000), (400, 500)]
+        );
+        assert_eq!(
+            RewardModule::volume_history(100, 400),
+            Err(Error::<Test>::RangeTooLong.into())
+        );
+        assert_eq!(
+            RewardModule::volume_history(0, 100),
+            Err(Error::<Test>::EraNotRetained.into())
+        );
+    });
+}
+
+#[test]
+fn test_custom_volume_conversion_should_be_used() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = RewardsPerEra::get();
+        VolumeConversions::set(0);
+        // volumes of 6 decimals, the fractions count in the shares
+        assert_ok!(RewardModule::save_trading(&alice, 1_500_000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 2_500_000, 100));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 4_000_000);
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_eq!(RewardModule::projected_reward(&alice), reward * 3 / 8);
+        assert_eq!(
+            RewardModule::era_share(&alice, 100),
+            Perquintill::from_rational(3u128, 8u128)
+        );
+        assert_ok!(RewardModule::save_trading(&alice, 1_000_000, 200));
+        assert_ok!(RewardModule::save_trading(&bob, 1_000_000, 200));
+        assert_eq!(RewardModule::rewards(&alice).confirmed, reward * 3 / 8);
+        assert_eq!(RewardModule::rewards(&bob).confirmed, reward * 5 / 8);
+        assert!(VolumeConversions::get() > 0);
+        VolumeConversions::set(0);
+
+        // the `u64` volumes take the reward math back saturating, and a boost past the
+        // bounds of the volume overflows instead of wrapping
+        assert_eq!(
+            <FixedPointVolume as Convert<u128, u64>>::convert(u128::MAX),
+            u64::MAX
+        );
+        EarlyBirdMultiplier::set(Perquintill::one());
+        assert_noop!(
+            RewardModule::save_trading(&alice, u64::MAX / 2 + 1, 300),
+            Error::<Test>::Overflow
+        );
+        EarlyBirdMultiplier::set(Perquintill::zero());
+    });
+}
+
+#[test]
+fn test_claim_and_lock_should_reserve_until_unlocked() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(300);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        Rewards::<Test>::insert(
+            &alice,
+            Reward {
+                confirmed: 10 * DOLLARS,
+                pending_vol: 0,
+                last_modify: 100,
+            },
+        );
+        assert_noop!(
+            RewardModule::unlock_reward(Origin::signed(alice.clone())),
+            Error::<Test>::LockNotFound
+        );
+        assert_ok!(RewardModule::claim_and_lock(
+            Origin::signed(alice.clone()),
+            50
+        ));
+        assert_eq!(Balances::reserved_balance(&alice), 10 * DOLLARS);
+        assert_eq!(Balances::free_balance(&alice), 0);
+        assert_eq!(
+            RewardModule::reward_locks(&alice),
+            Some((10 * DOLLARS, 350))
+        );
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardLocked(alice.clone(), 10 * DOLLARS, 350),
+        ));
+
+        frame_system::Pallet::<Test>::set_block_number(349);
+        assert_noop!(
+            RewardModule::unlock_reward(Origin::signed(alice.clone())),
+            Error::<Test>::RewardStillLocked
+        );
+        frame_system::Pallet::<Test>::set_block_number(350);
+        assert_ok!(RewardModule::unlock_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::reserved_balance(&alice), 0);
+        assert_eq!(Balances::free_balance(&alice), 10 * DOLLARS);
+        assert_eq!(RewardModule::reward_locks(&alice), None);
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardUnlocked(alice, 10 * DOLLARS),
+        ));
+    });
+}
+
+#[test]
+fn test_large_volume_should_be_observed() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let large = crate::mock::Event::RewardModule(crate::Event::LargeVolumeObserved(
+            alice.clone(),
+            1_000_001,
+            100,
+        ));
+        assert_ok!(RewardModule::save_trading(&alice, 1_000_000, 150));
+        assert!(!System::events().iter().any(|r| matches!(
+            r.event,
+            crate::mock::Event::RewardModule(crate::Event::LargeVolumeObserved(..))
+        )));
+        assert_ok!(RewardModule::save_trading(&alice, 1_000_001, 150));
+        System::assert_has_event(large);
+        // only flagged, the volume accrues as usual
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 2_000_001);
+    });
+}
+
+#[test]
+fn test_reaped_account_should_forfeit_rewards() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = RewardsPerEra::get();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 100));
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(RewardModule::rewards(&alice).confirmed, reward / 2);
+        assert_eq!(RewardModule::total_distributed(), reward / 2);
+
+        let _ = Balances::deposit_creating(&alice, 10 * DOLLARS);
+        Balances::make_free_balance_be(&alice, 0);
+        assert!(!Rewards::<Test>::contains_key(&alice));
+        assert_eq!(RewardModule::last_trade_block(&alice), None);
+        assert_eq!(RewardModule::total_distributed(), 0);
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardForfeited(alice, reward / 2),
+        ));
+
+        // the eras keep their totals, the others settle the same share
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 2000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(200)), 1000);
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 200));
+        assert_eq!(RewardModule::rewards(&bob).confirmed, reward / 2);
+    });
+}
+
+#[test]
+fn test_reaped_account_should_refund_pool_and_detach_sub_accounts() {
+    new_test_ext().execute_with(|| {
+        RewardsPerEra::set(4 * DOLLARS);
+        FundedEmission::set(true);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        let ferdie: AccountId = AccountKeyring::Ferdie.into();
+        let pool = RewardModule::pool_account();
+        Balances::make_free_balance_be(&ferdie, 10 * DOLLARS);
+        assert_ok!(RewardModule::fund_pool(
+            Origin::signed(ferdie.clone()),
+            5 * DOLLARS
+        ));
+        assert_ok!(RewardModule::set_sub_account_parent(
+            Origin::signed(alice.clone()),
+            Some(charlie.clone())
+        ));
+        assert_ok!(RewardModule::accept_sub_account(
+            Origin::signed(charlie.clone()),
+            alice.clone()
+        ));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(RewardModule::rewards(&charlie).confirmed, 4 * DOLLARS);
+        assert_eq!(RewardModule::pool_balance(), DOLLARS);
+
+        let _ = Balances::deposit_creating(&charlie, 10 * DOLLARS);
+        Balances::make_free_balance_be(&charlie, 0);
+        assert!(!Rewards::<Test>::contains_key(&charlie));
+        assert_eq!(RewardModule::pool_balance(), 5 * DOLLARS);
+        assert_eq!(Balances::free_balance(&pool), 5 * DOLLARS);
+        assert_eq!(RewardModule::total_distributed(), 0);
+
+        // the sub-account accrues to itself again
+        assert_eq!(RewardModule::sub_account_parent(&alice), None);
+        assert_eq!(SubAccounts::<Test>::iter_prefix(&charlie).count(), 0);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+        assert!(!Rewards::<Test>::contains_key(&charlie));
+        FundedEmission::set(false);
+        RewardsPerEra::set(1000000000000000000000000);
+    });
+}
+
+#[test]
+fn test_reaped_account_should_leave_no_state_behind() {
+    new_test_ext().execute_with(|| {
+        SharedPoolEngine::set(true);
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        assert_ok!(RewardModule::set_virtual_volume(
+            Origin::root(),
+            charlie.clone(),
+            1000
+        ));
+        assert_ok!(RewardModule::set_kyc_hold(Origin::root(), charlie.clone()));
+        assert_ok!(RewardModule::save_pool_trading(1, &charlie, 500, 100));
+        frame_system::Pallet::<Test>::set_block_number(100);
+        RewardModule::on_initialize(100);
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        assert_eq!(RewardModule::shares(&charlie).shares, 1000);
+        assert_eq!(RewardModule::total_shares(), 4000);
+
+        let _ = Balances::deposit_creating(&charlie, 10 * DOLLARS);
+        Balances::make_free_balance_be(&charlie, 0);
+        assert_eq!(RewardModule::total_shares(), 3000);
+
+        // the rotation credits nothing to the reaped account anymore
+        frame_system::Pallet::<Test>::set_block_number(200);
+        RewardModule::on_initialize(200);
+        assert!(!Rewards::<Test>::contains_key(&charlie));
+        assert!(!Shares::<Test>::contains_key(&charlie));
+        assert!(!VirtualVolume::<Test>::contains_key(&charlie));
+        assert!(!KycHold::<Test>::contains_key(&charlie));
+        assert!(!RewardLocks::<Test>::contains_key(&charlie));
+        assert!(!PoolRewards::<Test>::contains_key(1, &charlie));
+        assert_eq!(RewardModule::era_volumes(200, &charlie), 0);
+        assert_eq!(RewardModule::total_shares(), 3000);
+        SharedPoolEngine::set(false);
+    });
+}
+
+#[test]
+fn test_claim_past_retention_should_expire() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        Rewards::<Test>::mutate(&alice, |r| r.confirmed = DOLLARS);
+        // the pruned total of the pending era reads as zero
+        Volumes::<Test>::remove(RewardModule::era_of(100));
+        frame_system::Pallet::<Test>::set_block_number(550);
+        // the expired pending volume confirms nothing, the confirmed rewards are still paid
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardExpired(alice.clone(), 1000, RewardModule::current_era_index(100)),
+        ));
+        assert!(!System::events().iter().any(|e| matches!(
+            e.event,
+            crate::mock::Event::RewardModule(crate::Event::RewardConfirmed(..))
+        )));
+        assert_eq!(Balances::free_balance(&alice), DOLLARS);
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 0);
+
+        // the last retained era is still settled
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 200));
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(Balances::free_balance(&bob), RewardsPerEra::get());
+    });
+}
+
+#[test]
+fn test_total_pending_volume_should_equal_sum_of_rewards() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        let check = || {
+            let sum = Rewards::<Test>::iter().fold(0u64, |acc, (_, r)| acc + r.pending_vol);
+            assert_eq!(RewardModule::total_pending_volume(), sum);
+        };
+        frame_system::Pallet::<Test>::set_block_number(150);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        assert_ok!(RewardModule::save_trading(&alice, 500, 150));
+        check();
+        assert_eq!(RewardModule::total_pending_volume(), 4500);
+        assert_ok!(RewardModule::revoke_trading(&bob, 1000, 150));
+        check();
+        assert_ok!(RewardModule::import_volume_snapshot(
+            Origin::root(),
+            vec![(charlie.clone(), 700, 100)].try_into().unwrap()
+        ));
+        check();
+
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_ok!(RewardModule::save_trading(&alice, 200, 250));
+        check();
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_ok!(RewardModule::take_reward(Origin::signed(charlie.clone())));
+        check();
+        assert_eq!(RewardModule::total_pending_volume(), 200);
+        RewardModule::on_killed_account(&alice);
+        check();
+        assert_eq!(RewardModule::total_pending_volume(), 0);
+    });
+}
+
+#[test]
+fn test_emission_schedules_should_stack() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let base = RewardsPerEra::get();
+        assert_noop!(
+            RewardModule::schedule_emission(Origin::root(), 300, 200, 10),
+            Error::<Test>::InvalidSchedule
+        );
+        assert_ok!(RewardModule::schedule_emission(
+            Origin::root(),
+            150,
+            350,
+            100
+        ));
+        assert_ok!(RewardModule::schedule_emission(
+            Origin::root(),
+            300,
+            400,
+            10
+        ));
+        assert_noop!(
+            RewardModule::schedule_emission(Origin::root(), 100, 100, 1),
+            Error::<Test>::TooManySchedules
+        );
+        assert_eq!(RewardModule::era_reward_at(0), base);
+        assert_eq!(RewardModule::era_reward_at(100), base + 100);
+        assert_eq!(RewardModule::era_reward_at(200), base + 100);
+        assert_eq!(RewardModule::era_reward_at(300), base + 110);
+        assert_eq!(RewardModule::era_reward_at(400), base + 10);
+        assert_eq!(RewardModule::era_reward_at(500), base);
+
+        for era in [100, 200, 300, 400] {
+            frame_system::Pallet::<Test>::set_block_number(era);
+            assert_ok!(RewardModule::save_trading(&alice, 1000, era));
+        }
+        frame_system::Pallet::<Test>::set_block_number(500);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 500));
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            4 * base + 100 + 100 + 110 + 10
+        );
+
+        // the ended schedules are kept for the closed eras still to be settled
+        assert_noop!(
+            RewardModule::schedule_emission(Origin::root(), 500, 600, 1),
+            Error::<Test>::TooManySchedules
+        );
+
+        // and make room for new ones once their eras are pruned
+        for now in 800..803 {
+            frame_system::Pallet::<Test>::set_block_number(now);
+            RewardModule::on_initialize(now);
+        }
+        assert_eq!(RewardModule::prune_cursor(), 500);
+        assert_ok!(RewardModule::schedule_emission(Origin::root(), 800, 900, 1));
+        assert_eq!(RewardModule::emission_schedules(), vec![(800, 900, 1)]);
+    });
+}
+
+#[test]
+fn test_era_rank_should_order_by_volume() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        let dave: AccountId = AccountKeyring::Dave.into();
+        assert_ok!(RewardModule::save_trading(&alice, 2000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 5000, 120));
+        assert_ok!(RewardModule::save_trading(&charlie, 1000, 180));
+        assert_eq!(RewardModule::era_rank(&bob, 100), (1, 3));
+        assert_eq!(RewardModule::era_rank(&alice, 150), (2, 3));
+        assert_eq!(RewardModule::era_rank(&charlie, 100), (3, 3));
+        assert_eq!(RewardModule::era_rank(&dave, 100), (0, 3));
+        assert_eq!(RewardModule::era_rank(&alice, 200), (0, 0));
+    });
+}
+
+#[test]
+fn test_self_trade_should_only_count_in_totals() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading_pair(&alice, &bob, 1000, 150));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+        assert_eq!(RewardModule::rewards(&bob).pending_vol, 1000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 2000);
+
+        assert_ok!(RewardModule::save_trading_pair(&alice, &alice, 500, 150));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+        assert_eq!(RewardModule::era_volumes(100, &alice), 1000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 3000);
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::SelfTradeExcluded(alice.clone(), 500),
+        ));
+
+        // both sides accrue without the exclusion
+        ExcludeSelfTrades::set(false);
+        assert_ok!(RewardModule::save_trading_pair(&alice, &alice, 500, 150));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 2000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 4000);
+    });
+}
+
+#[test]
+fn test_self_trade_should_go_through_the_trading_pipeline() {
+    use frame_support::traits::OnFinalize;
+    let alice: AccountId = AccountKeyring::Alice.into();
+    let exchange: AccountId = AccountKeyring::Ferdie.into();
+    let run = |batch: bool| {
+        new_test_ext().execute_with(|| {
+            BatchSettlement::set(batch);
+            frame_system::Pallet::<Test>::set_block_number(150);
+            assert_ok!(RewardModule::schedule_boost(
+                Origin::root(),
+                100,
+                150,
+                Perquintill::from_percent(100)
+            ));
+            assert_ok!(RewardModule::set_sub_account_parent(
+                Origin::signed(alice.clone()),
+                Some(exchange.clone())
+            ));
+            assert_ok!(RewardModule::accept_sub_account(
+                Origin::signed(exchange.clone()),
+                alice.clone()
+            ));
+            assert_ok!(RewardModule::save_trading_pair(&alice, &alice, 500, 150));
+            System::assert_has_event(crate::mock::Event::RewardModule(
+                crate::Event::SelfTradeExcluded(alice.clone(), 500),
+            ));
+            RewardModule::on_finalize(150);
+            BatchSettlement::set(false);
+            (
+                RewardModule::volumes(RewardModule::era_of(100)),
+                RewardModule::self_trade_volumes(100),
+                RewardModule::era_volumes(100, &alice),
+                RewardModule::era_volumes(100, &exchange),
+                RewardModule::participants(100),
+            )
+        })
+    };
+    // both boosted sides count in the total, none in the volumes of the accounts
+    assert_eq!(run(false), (2000, 2000, 0, 0, 0));
+    assert_eq!(run(true), run(false));
+}
+
+#[test]
+fn test_era_snapshot_should_be_immutable() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        assert_noop!(
+            RewardModule::snapshot_era(Origin::signed(alice.clone()), 100),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            RewardModule::snapshot_era(Origin::root(), 100),
+            Error::<Test>::EraNotClosed
+        );
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::snapshot_era(Origin::root(), 150));
+        let snapshot = RewardModule::era_snapshots(100).unwrap();
+        assert_eq!(snapshot.total_vol, 4000);
+        let mut participants = snapshot.participants.clone();
+        participants.sort();
+        let mut expected = vec![(alice.clone(), 1000), (bob.clone(), 3000)];
+        expected.sort();
+        assert_eq!(participants, expected);
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::EraSnapshotted(100, 4000, 2),
+        ));
+
+        // neither later changes nor another snapshot alter the record
+        Volumes::<Test>::remove(RewardModule::era_of(100));
+        EraVolumes::<Test>::remove(100, &bob);
+        assert_noop!(
+            RewardModule::snapshot_era(Origin::root(), 100),
+            Error::<Test>::AlreadySnapshotted
+        );
+        assert_eq!(RewardModule::era_snapshots(100), Some(snapshot));
+    });
+}
+
+#[test]
+fn test_virtual_volume_should_share_the_pool() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = RewardsPerEra::get();
+        assert_ok!(RewardModule::set_virtual_volume(
+            Origin::root(),
+            alice.clone(),
+            1000
+        ));
+        assert_noop!(
+            RewardModule::set_virtual_volume(Origin::root(), bob.clone(), 1000),
+            Error::<Test>::TooManyVirtualAccounts
+        );
+
+        frame_system::Pallet::<Test>::set_block_number(100);
+        RewardModule::on_initialize(100);
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 4000);
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        RewardModule::on_initialize(200);
+        assert_eq!(RewardModule::rewards(&alice).confirmed, reward / 4);
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+        assert_ok!(RewardModule::save_trading(&bob, 1, 200));
+        assert_eq!(RewardModule::rewards(&bob).confirmed, reward / 4 * 3);
+
+        // removed from the allowlist, nothing is accrued anymore
+        assert_ok!(RewardModule::set_virtual_volume(
+            Origin::root(),
+            alice.clone(),
+            0
+        ));
+        frame_system::Pallet::<Test>::set_block_number(300);
+        RewardModule::on_initialize(300);
+        assert_eq!(RewardModule::rewards(&alice).last_modify, 200);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(300)), 0);
+    });
+}
+
+#[test]
+fn test_reconcile_era_should_fix_drift() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        // the excluded self-trade only counts in the total of the era
+        assert_ok!(RewardModule::save_trading_pair(&bob, &bob, 500, 150));
+        Volumes::<Test>::insert(RewardModule::era_of(100), 5500);
+        assert_noop!(
+            RewardModule::reconcile_era(Origin::signed(alice.clone()), 100, 10),
+            DispatchError::BadOrigin
+        );
+        // the first page leaves the total untouched
+        assert_ok!(RewardModule::reconcile_era(Origin::root(), 150, 1));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 5500);
+        assert!(RewardModule::reconcile_progress(100).is_some());
+        assert_ok!(RewardModule::reconcile_era(Origin::root(), 150, 1));
+        assert!(RewardModule::reconcile_progress(100).is_none());
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 5000);
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::EraReconciled(100, 5500, 5000),
+        ));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_noop!(
+            RewardModule::reconcile_era(Origin::root(), 100, 10),
+            Error::<Test>::EraAlreadyClosed
+        );
+    });
+}
+
+#[test]
+fn test_first_trade_of_era_should_be_boosted() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        EarlyBirdMultiplier::set(Perquintill::from_percent(50));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1500);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 160));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 2500);
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 170));
+        assert_eq!(RewardModule::rewards(&bob).pending_vol, 1500);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 4000);
+
+        // boosted again in the next era
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 250));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1500);
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            RewardsPerEra::get() / 8 * 5
+        );
+    });
+}
+
+#[test]
+fn test_claim_without_reward_entry_should_claim_nothing() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(250);
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        assert!(!Rewards::<Test>::contains_key(&charlie));
+        assert_ok!(RewardModule::take_reward(Origin::signed(charlie.clone())));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardClaimed(charlie.clone(), 0),
+        ));
+        assert_eq!(RewardModule::rewards(&charlie), Default::default());
+        assert_eq!(Balances::free_balance(&charlie), 0);
+        assert_eq!(RewardModule::last_claim(&charlie), None);
+    });
+}
+
+#[test]
+fn test_reward_delta_since_should_sum_later_eras() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = RewardsPerEra::get();
+        for era in [100, 200, 300] {
+            frame_system::Pallet::<Test>::set_block_number(era);
+            assert_ok!(RewardModule::save_trading(&alice, 1000, era));
+            assert_ok!(RewardModule::save_trading(&bob, 3000, era));
+        }
+        frame_system::Pallet::<Test>::set_block_number(400);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 400));
+        assert_eq!(RewardModule::era_rewards(100, &alice), reward / 4);
+        assert_eq!(RewardModule::reward_delta_since(&alice, 0), reward / 4 * 3);
+        assert_eq!(
+            RewardModule::reward_delta_since(&alice, 100),
+            reward / 4 * 2
+        );
+        assert_eq!(RewardModule::reward_delta_since(&alice, 250), reward / 4);
+        assert_eq!(RewardModule::reward_delta_since(&alice, 300), 0);
+        // bob hasn't settled anything yet
+        assert_eq!(RewardModule::reward_delta_since(&bob, 0), 0);
+
+        // only the retained eras are summed
+        frame_system::Pallet::<Test>::set_block_number(500);
+        assert_eq!(RewardModule::reward_delta_since(&alice, 0), reward / 4 * 2);
+    });
+}
+
+#[test]
+fn test_claim_below_min_claimable_should_be_rejected() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        MinClaimable::set(3 * DOLLARS);
+        RewardsPerEra::set(4 * DOLLARS);
+        frame_system::Pallet::<Test>::set_block_number(100);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 200));
+        assert_noop!(
+            RewardModule::take_reward(Origin::signed(alice.clone())),
+            Error::<Test>::BelowMinClaim
+        );
+        assert_eq!(RewardModule::rewards(&alice).confirmed, 2 * DOLLARS);
+
+        // the claimable of the closed era counts towards the minimum
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_eq!(RewardModule::total_claimable(&alice), 4 * DOLLARS);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), 4 * DOLLARS);
+    });
+}
+
+#[test]
+fn test_transfer_pending_volume_should_conserve_era_volume() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        let reward = RewardsPerEra::get();
+        frame_system::Pallet::<Test>::set_block_number(150);
+        assert_ok!(RewardModule::save_trading(&alice, 3000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 150));
+        assert_noop!(
+            RewardModule::transfer_pending_volume(Origin::signed(alice.clone()), alice.clone(), 1),
+            Error::<Test>::SelfTransfer
+        );
+        assert_noop!(
+            RewardModule::transfer_pending_volume(
+                Origin::signed(alice.clone()),
+                charlie.clone(),
+                3001
+            ),
+            Error::<Test>::InsufficientVolume
+        );
+        assert_ok!(RewardModule::transfer_pending_volume(
+            Origin::signed(alice.clone()),
+            charlie.clone(),
+            1000
+        ));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 2000);
+        assert_eq!(RewardModule::rewards(&charlie).pending_vol, 1000);
+        assert_eq!(RewardModule::era_volumes(100, &charlie), 1000);
+        assert_eq!(RewardModule::participants(100), 3);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 4000);
+        assert_eq!(RewardModule::total_pending_volume(), 4000);
+
+        // the pending volume of a closed era can't be moved anymore
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_noop!(
+            RewardModule::transfer_pending_volume(Origin::signed(bob.clone()), charlie.clone(), 1),
+            Error::<Test>::InsufficientVolume
+        );
+        for who in [&alice, &bob, &charlie] {
+            assert_ok!(RewardModule::save_trading(who, 1, 250));
+        }
+        assert_eq!(RewardModule::rewards(&alice).confirmed, reward / 2);
+        assert_eq!(RewardModule::rewards(&bob).confirmed, reward / 4);
+        assert_eq!(RewardModule::rewards(&charlie).confirmed, reward / 4);
+    });
+}
+
+/// xorshift64, enough to drive the randomized sequences reproducibly from a seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+}
+
+#[test]
+fn test_rotate_reward_invariants_should_hold_across_random_gaps() {
+    let traders: Vec<AccountId> = vec![
+        AccountKeyring::Alice.into(),
+        AccountKeyring::Bob.into(),
+        AccountKeyring::Charlie.into(),
+        AccountKeyring::Dave.into(),
+    ];
+    for seed in 1..=64u64 {
+        new_test_ext().execute_with(|| {
+            let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let mut earned = vec![0u128; traders.len()];
+            let mut eras = Vec::new();
+            let mut now = 100;
+            for _ in 0..40 {
+                // stay in the era, or skip up to three eras ahead
+                now += match rng.next(3) {
+                    0 => rng.next(10) as u32,
+                    _ => 100 * (1 + rng.next(3) as u32),
+                };
+                frame_system::Pallet::<Test>::set_block_number(now);
+                eras.push(now - now % 100);
+                let i = rng.next(traders.len() as u64) as usize;
+                let who = &traders[i];
+                if rng.next(4) == 0 {
+                    // an account idle beyond the retained eras settles nothing of its pending era
+                    let r = RewardModule::rewards(who);
+                    let expired = r.pending_vol > 0 && r.last_modify + 300 < now - now % 100;
+                    assert_ok!(RewardModule::take_reward(Origin::signed(who.clone())));
+                    if expired {
+                        System::assert_has_event(crate::mock::Event::RewardModule(
+                            crate::Event::RewardExpired(
+                                who.clone(),
+                                r.pending_vol,
+                                RewardModule::current_era_index(r.last_modify),
+                            ),
+                        ));
+                    }
+                } else {
+                    let vol = 1 + rng.next(1_000_000);
+                    assert_ok!(RewardModule::save_trading(who, vol, now));
+                }
+                for (i, who) in traders.iter().enumerate() {
+                    let r = RewardModule::rewards(who);
+                    // the confirmed rewards only grow, the claims move them to the balance
+                    let total = Balances::free_balance(who) + r.confirmed;
+                    assert!(total >= earned[i], "seed {}", seed);
+                    earned[i] = total;
+                    assert!(
+                        r.pending_vol <= RewardModule::volumes(RewardModule::era_of(r.last_modify)),
+                        "seed {}",
+                        seed
+                    );
+                }
+                for era in eras.iter() {
+                    let confirmed = traders
+                        .iter()
+                        .fold(0u128, |acc, who| acc + RewardModule::era_rewards(era, who));
+                    assert!(
+                        confirmed <= RewardModule::traders_pool(*era),
+                        "seed {}",
+                        seed
+                    );
+                    assert!(
+                        RewardModule::era_distributed(era) <= RewardModule::traders_pool(*era),
+                        "seed {}",
+                        seed
+                    );
+                }
+            }
+        });
+    }
+}
+
+#[test]
+fn test_referral_chain_should_pay_each_level() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        let dave: AccountId = AccountKeyring::Dave.into();
+        let eve: AccountId = AccountKeyring::Eve.into();
+        let reward = RewardsPerEra::get();
+        ReferralLevels::set(4);
+        for (who, referrer) in [(&alice, &bob), (&bob, &charlie), (&charlie, &dave)] {
+            assert_ok!(RewardModule::bind_referrer(
+                Origin::signed(who.clone()),
+                referrer.clone()
+            ));
+        }
+        // a cycle back to the trader pays nobody twice
+        assert_ok!(RewardModule::bind_referrer(
+            Origin::signed(dave.clone()),
+            alice.clone()
+        ));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            reward - reward / 10 - reward / 20 - reward / 50
+        );
+        assert_eq!(RewardModule::rewards(&bob).confirmed, reward / 10);
+        assert_eq!(RewardModule::rewards(&charlie).confirmed, reward / 20);
+        assert_eq!(RewardModule::rewards(&dave).confirmed, reward / 50);
+
+        // the cap cuts the upper levels first
+        MaxReferralShare::set(Perquintill::from_percent(12));
+        assert_ok!(RewardModule::bind_referrer(
+            Origin::signed(eve.clone()),
+            bob.clone()
+        ));
+        assert_ok!(RewardModule::save_trading(&eve, 1000, 200));
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_ok!(RewardModule::save_trading(&eve, 1000, 300));
+        assert_eq!(
+            RewardModule::rewards(&eve).confirmed,
+            reward / 2 - reward / 20 - reward / 2 / 50
+        );
+        assert_eq!(
+            RewardModule::rewards(&bob).confirmed,
+            reward / 10 + reward / 20
+        );
+        assert_eq!(
+            RewardModule::rewards(&charlie).confirmed,
+            reward / 20 + reward / 2 / 50
+        );
+        assert_eq!(RewardModule::rewards(&dave).confirmed, reward / 50);
+    });
+}
+
+#[test]
+fn test_boosted_referred_rewards_should_not_exceed_the_pool() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(100);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        let dave: AccountId = AccountKeyring::Dave.into();
+        ReferralLevels::set(2);
+        assert_ok!(RewardModule::schedule_boost(
+            Origin::root(),
+            100,
+            150,
+            Perquintill::from_percent(100)
+        ));
+        for (who, referrer) in [(&alice, &bob), (&bob, &charlie), (&dave, &alice)] {
+            assert_ok!(RewardModule::bind_referrer(
+                Origin::signed(who.clone()),
+                referrer.clone()
+            ));
+        }
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 120));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 140));
+        assert_ok!(RewardModule::save_trading(&dave, 1000, 160));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        for who in [&alice, &bob, &dave] {
+            assert_ok!(RewardModule::take_reward(Origin::signed(who.clone())));
+        }
+        assert_ok!(RewardModule::take_reward(Origin::signed(charlie.clone())));
+        let pool = RewardModule::traders_pool(100);
+        let confirmed: u128 = [&alice, &bob, &charlie, &dave]
+            .iter()
+            .map(|who| Balances::free_balance(*who) + RewardModule::rewards(*who).confirmed)
+            .sum();
+        assert!(confirmed <= pool);
+        assert!(confirmed + 3 >= pool);
+        assert!(RewardModule::total_distributed() <= pool);
+        ReferralLevels::set(1);
+    });
+}
+
+#[test]
+fn test_claim_with_tip_should_split_the_reward() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(300);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let keeper: AccountId = AccountKeyring::Ferdie.into();
+        Rewards::<Test>::insert(
+            &alice,
+            Reward {
+                confirmed: 10 * DOLLARS,
+                pending_vol: 0,
+                last_modify: 100,
+            },
+        );
+        let tip = Perquintill::from_percent(10);
+        assert_noop!(
+            RewardModule::claim_with_tip(Origin::signed(keeper.clone()), alice.clone(), tip),
+            Error::<Test>::NotCrankable
+        );
+        assert_ok!(RewardModule::opt_in_cranking(
+            Origin::signed(alice.clone()),
+            Some(tip)
+        ));
+        assert_noop!(
+            RewardModule::claim_with_tip(
+                Origin::signed(keeper.clone()),
+                alice.clone(),
+                Perquintill::from_percent(11)
+            ),
+            Error::<Test>::TipTooHigh
+        );
+        assert_ok!(RewardModule::claim_with_tip(
+            Origin::signed(keeper.clone()),
+            alice.clone(),
+            tip
+        ));
+        assert_eq!(Balances::free_balance(&alice), 9 * DOLLARS);
+        assert_eq!(Balances::free_balance(&keeper), 1 * DOLLARS);
+        assert!(!Rewards::<Test>::contains_key(&alice));
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::CrankTipPaid(keeper.clone(), alice.clone(), 1 * DOLLARS),
+        ));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardClaimed(alice.clone(), 9 * DOLLARS),
+        ));
+
+        // opting out stops the keepers
+        assert_ok!(RewardModule::opt_in_cranking(
+            Origin::signed(alice.clone()),
+            None
+        ));
+        assert_noop!(
+            RewardModule::claim_with_tip(Origin::signed(keeper), alice, tip),
+            Error::<Test>::NotCrankable
+        );
+    });
+}
+
+#[test]
+fn test_max_possible_reward_should_be_the_traders_pool() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = RewardsPerEra::get();
+        OperatorShare::set(Perquintill::from_percent(20));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_ok!(RewardModule::save_trading(&alice, 1, 250));
+        let max = RewardModule::max_possible_reward(&alice, 150);
+        assert_eq!(max, reward / 5 * 4);
+        assert_eq!(RewardModule::era_rewards(100, &alice), max / 4);
+        assert!(RewardModule::era_rewards(100, &alice) < max);
+    });
+}
+
+#[test]
+fn test_claim_reexecuted_should_not_credit_twice() {
+    use frame_support::storage::{with_transaction, TransactionOutcome};
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let reward = RewardsPerEra::get();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        frame_system::Pallet::<Test>::set_block_number(250);
+
+        // a discarded fork executing the claim leaves the pre-state untouched
+        with_transaction(|| {
+            assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+            assert_eq!(Balances::free_balance(&alice), reward);
+            TransactionOutcome::Rollback(())
+        });
+        assert_eq!(Balances::free_balance(&alice), 0);
+        assert_eq!(RewardModule::total_claimable(&alice), reward);
+
+        // the same pre-state pays the same amount, the claimed state pays nothing
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), reward);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardClaimed(alice.clone(), 0),
+        ));
+        assert_eq!(Balances::free_balance(&alice), reward);
+        assert_eq!(RewardModule::total_claimed(), reward);
+    });
+}
+
+#[test]
+fn test_claim_should_vest_beyond_instant_share() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(300);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        InstantShare::set(Perquintill::from_percent(40));
+        Rewards::<Test>::insert(
+            &alice,
+            Reward {
+                confirmed: 10 * DOLLARS,
+                pending_vol: 0,
+                last_modify: 100,
+            },
+        );
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), 4 * DOLLARS);
+        assert_eq!(Balances::reserved_balance(&alice), 6 * DOLLARS);
+        assert_eq!(
+            RewardModule::vesting(&alice),
+            Some(VestingInfo {
+                total: 6 * DOLLARS,
+                released: 0,
+                start: 300,
+                end: 400,
+            })
+        );
+        // the instant part is spendable right away
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(Balances::transfer(
+            Origin::signed(alice.clone()),
+            bob.into(),
+            2 * DOLLARS
+        ));
+
+        frame_system::Pallet::<Test>::set_block_number(350);
+        assert_ok!(RewardModule::vest(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), 5 * DOLLARS);
+        assert_eq!(Balances::reserved_balance(&alice), 3 * DOLLARS);
+        System::assert_last_event(crate::mock::Event::RewardModule(crate::Event::Vested(
+            alice.clone(),
+            3 * DOLLARS,
+        )));
+
+        frame_system::Pallet::<Test>::set_block_number(450);
+        assert_ok!(RewardModule::vest(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), 8 * DOLLARS);
+        assert_eq!(Balances::reserved_balance(&alice), 0);
+        assert_eq!(RewardModule::vesting(&alice), None);
+    });
+}
+
+#[test]
+fn test_token_pools_should_split_among_own_traders() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let (btc, eth) = (1u32, 2u32);
+        assert_ok!(RewardModule::set_pool_rewards_per_era(
+            Origin::root(),
+            TokenAsPool::convert(btc),
+            6 * DOLLARS
+        ));
+        assert_ok!(RewardModule::set_pool_rewards_per_era(
+            Origin::root(),
+            TokenAsPool::convert(eth),
+            2 * DOLLARS
+        ));
+        assert_ok!(RewardModule::save_token_trading(btc, &alice, 1000, 100));
+        assert_ok!(RewardModule::save_token_trading(btc, &bob, 2000, 130));
+        assert_ok!(RewardModule::save_token_trading(eth, &bob, 10, 140));
+        assert_eq!(RewardModule::token_volumes(btc, 199), 3000);
+        assert_eq!(RewardModule::token_volumes(eth, 150), 10);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 0);
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_pool_reward(
+            Origin::signed(alice.clone()),
+            TokenAsPool::convert(btc)
+        ));
+        assert_eq!(Balances::free_balance(&alice), 2 * DOLLARS);
+        assert_ok!(RewardModule::take_pool_reward(
+            Origin::signed(alice.clone()),
+            TokenAsPool::convert(eth)
+        ));
+        assert_eq!(Balances::free_balance(&alice), 2 * DOLLARS);
+        assert_ok!(RewardModule::take_pool_reward(
+            Origin::signed(bob.clone()),
+            TokenAsPool::convert(btc)
+        ));
+        assert_ok!(RewardModule::take_pool_reward(
+            Origin::signed(bob.clone()),
+            TokenAsPool::convert(eth)
+        ));
+        assert_eq!(Balances::free_balance(&bob), 6 * DOLLARS);
+    });
+}
+
+#[test]
+fn test_token_and_pair_volumes_should_be_finalized_and_pruned() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let btc = 1u32;
+        assert_ok!(RewardModule::set_pool_rewards_per_era(
+            Origin::root(),
+            TokenAsPool::convert(btc),
+            DOLLARS
+        ));
+        assert_ok!(RewardModule::save_token_trading(btc, &alice, 1000, 150));
+        assert_ok!(RewardModule::save_pair_trading(Some(1), &alice, 1000, 150));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_pool_reward(
+            Origin::signed(alice.clone()),
+            TokenAsPool::convert(btc)
+        ));
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_noop!(
+            RewardModule::save_token_trading(btc, &alice, 1, 199),
+            Error::<Test>::EraFinalized
+        );
+        assert_noop!(
+            RewardModule::save_pair_trading(Some(1), &alice, 1, 199),
+            Error::<Test>::EraFinalized
+        );
+        assert_eq!(RewardModule::token_volumes(btc, 150), 1000);
+        assert_eq!(RewardModule::pair_volume(1, 150), 1000);
+
+        RewardModule::on_initialize(500);
+        assert_eq!(RewardModule::token_volumes(btc, 150), 0);
+        assert_eq!(RewardModule::pair_volume(1, 150), 0);
+    });
+}
+
+#[test]
+fn test_block_volume_should_be_capped() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        MaxVolumePerBlock::set(1000);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 600, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 300, 150));
+        assert_noop!(
+            RewardModule::save_trading(&alice, 101, 150),
+            Error::<Test>::BlockVolumeCapExceeded
+        );
+        assert_ok!(RewardModule::save_trading(&bob, 100, 150));
+        assert_eq!(RewardModule::block_volume(), 1000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 1000);
+
+        RewardModule::on_initialize(151);
+        assert_eq!(RewardModule::block_volume(), 0);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 151));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1600);
+
+        // the self-trades and the pool trades count too
+        RewardModule::on_initialize(152);
+        assert_noop!(
+            RewardModule::save_trading_pair(&bob, &bob, 501, 152),
+            Error::<Test>::BlockVolumeCapExceeded
+        );
+        assert_ok!(RewardModule::save_trading_pair(&bob, &bob, 500, 152));
+        assert_eq!(RewardModule::last_trade_block(&bob), Some(152));
+        assert_noop!(
+            RewardModule::save_pool_trading(1, &alice, 1, 152),
+            Error::<Test>::BlockVolumeCapExceeded
+        );
+
+        // a trade into a finalized era is rejected before it takes any of the cap
+        RewardModule::on_initialize(153);
+        FinalizedEras::<Test>::insert(100, true);
+        assert_noop!(
+            RewardModule::save_trading(&alice, 1000, 153),
+            Error::<Test>::EraFinalized
+        );
+        assert_noop!(
+            RewardModule::save_trading_pair(&bob, &bob, 500, 153),
+            Error::<Test>::EraFinalized
+        );
+        assert_eq!(RewardModule::block_volume(), 0);
+        MaxVolumePerBlock::set(u64::MAX);
+    });
+}
+
+#[test]
+fn test_boost_window_should_span_eras() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_noop!(
+            RewardModule::schedule_boost(Origin::root(), 260, 250, Perquintill::one()),
+            Error::<Test>::InvalidSchedule
+        );
+        assert_ok!(RewardModule::schedule_boost(
+            Origin::root(),
+            180,
+            220,
+            Perquintill::one()
+        ));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::BoostScheduled(180, 220, Perquintill::one()),
+        ));
+
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 179));
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 180));
+        assert_eq!(RewardModule::era_volumes(100, &alice), 1000);
+        assert_eq!(RewardModule::era_volumes(100, &bob), 2000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 3000);
+
+        frame_system::Pallet::<Test>::set_block_number(221);
+        assert_ok!(RewardModule::save_trading(&alice, 500, 220));
+        assert_ok!(RewardModule::save_trading(&bob, 500, 221));
+        assert_eq!(RewardModule::era_volumes(200, &alice), 1000);
+        assert_eq!(RewardModule::era_volumes(200, &bob), 500);
+        assert_eq!(RewardModule::rewards(&bob).pending_vol, 500);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(200)), 1500);
+    });
+}
+
+#[test]
+fn test_first_trade_should_never_reference_era_zero() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(550);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert!(!Rewards::<Test>::contains_key(&alice));
+        RewardModule::ensure_initialized(&alice, 550);
+        assert_eq!(
+            RewardModule::rewards(&alice),
+            Reward {
+                confirmed: 0,
+                pending_vol: 0,
+                last_modify: 500
+            }
+        );
+        RewardModule::ensure_initialized(&alice, 650);
+        assert_eq!(RewardModule::rewards(&alice).last_modify, 500);
+
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 550));
+        assert_eq!(
+            RewardModule::rewards(&bob),
+            Reward {
+                confirmed: 0,
+                pending_vol: 1000,
+                last_modify: 500
+            }
+        );
+        assert_eq!(RewardModule::era_rewards(0, &bob), 0);
+        assert!(!System::events().iter().any(|r| matches!(
+            r.event,
+            crate::mock::Event::RewardModule(crate::Event::RewardConfirmed(..))
+        )));
+    });
+}
+
+#[test]
+fn test_reward_breakdown_should_match_storage() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_eq!(RewardModule::reward_breakdown(&alice), (0, 0, 0, 100));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_ok!(RewardModule::save_trading(&alice, 500, 250));
+        let reward = RewardModule::rewards(&alice);
+        assert_eq!(reward.confirmed, RewardsPerEra::get() / 4);
+        assert_eq!(
+            RewardModule::reward_breakdown(&alice),
+            (
+                reward.confirmed,
+                RewardModule::projected_reward(&alice),
+                500,
+                200
+            )
+        );
+        assert_eq!(RewardModule::projected_reward(&alice), RewardsPerEra::get());
+    });
+}
+
+#[test]
+fn test_confirmations_should_round_to_unit() {
+    new_test_ext().execute_with(|| {
+        RewardsPerEra::set(10 * DOLLARS);
+        RoundToUnit::set(DOLLARS);
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(Balances::free_balance(&alice), 2 * DOLLARS);
+        assert_eq!(Balances::free_balance(&bob), 7 * DOLLARS);
+        assert_eq!(RewardModule::era_rewards(100, &alice) % DOLLARS, 0);
+        assert_eq!(RewardModule::era_dust(100), DOLLARS);
+        assert_eq!(RewardModule::era_distributed(100), 9 * DOLLARS);
+        RoundToUnit::set(0);
+        RewardsPerEra::set(1000000000000000000000000);
+    });
+}
+
+#[test]
+fn test_eligibility_should_reflect_kyc_holds() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let exchange: AccountId = AccountKeyring::Ferdie.into();
+        assert!(RewardModule::is_eligible(&alice));
+        assert_ok!(RewardModule::set_kyc_hold(Origin::root(), alice.clone()));
+        assert!(!RewardModule::is_eligible(&alice));
+        assert_ok!(RewardModule::clear_kyc_hold(Origin::root(), alice.clone()));
+        assert!(RewardModule::is_eligible(&alice));
+
+        assert_ok!(RewardModule::set_sub_account_parent(
+            Origin::signed(alice.clone()),
+            Some(exchange.clone())
+        ));
+        assert_ok!(RewardModule::accept_sub_account(
+            Origin::signed(exchange.clone()),
+            alice.clone()
+        ));
+        assert_ok!(RewardModule::set_kyc_hold(Origin::root(), exchange.clone()));
+        assert!(!RewardModule::is_eligible(&alice));
+        assert_ok!(RewardModule::clear_kyc_hold(
+            Origin::root(),
+            exchange.clone()
+        ));
+        assert!(RewardModule::is_eligible(&alice));
+    });
+}
+
+#[test]
+fn test_era_close_should_prune_expired_volumes() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        RewardModule::on_initialize(200);
+        System::assert_last_event(crate::mock::Event::RewardModule(crate::Event::EraClosed(
+            100,
+            1000,
+            1,
+            RewardsPerEra::get(),
+        )));
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 1000);
+
+        frame_system::Pallet::<Test>::set_block_number(500);
+        RewardModule::on_initialize(500);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 0);
+        assert_ok!(RewardModule::save_trading(&alice, 500, 500));
+        assert_eq!(
+            RewardModule::rewards(&alice),
+            Reward {
+                confirmed: 0,
+                pending_vol: 500,
+                last_modify: 500
+            }
+        );
+    });
+}
+
+#[test]
+fn test_pruning_should_clear_all_era_data() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        ExactDistribution::set(true);
+        OperatorShare::set(Perquintill::from_percent(20));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        RewardModule::on_initialize(200);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        EraDust::<Test>::insert(100, 1);
+        EraBonusPool::<Test>::insert(100, DOLLARS);
+        assert!(RewardModule::finalized_eras(100));
+        assert!(RewardModule::operator_paid(100));
+        assert_eq!(RewardModule::participants(100), 1);
+
+        frame_system::Pallet::<Test>::set_block_number(500);
+        RewardModule::on_initialize(500);
+        assert_eq!(RewardModule::prune_cursor(), 200);
+        assert_eq!(RewardModule::era_volumes(100, &alice), 0);
+        assert_eq!(RewardModule::participants(100), 0);
+        assert_eq!(RewardModule::era_distributed(100), 0);
+        assert!(!RewardModule::finalized_eras(100));
+        assert!(!AllocatedEras::<Test>::get(100));
+        assert_eq!(EraDust::<Test>::get(100), 0);
+        assert!(!RewardModule::operator_paid(100));
+        assert_eq!(EraBonusPool::<Test>::get(100), 0);
+        assert_eq!(RewardModule::era_volumes(200, &alice), 1000);
+        // a pruned era stays closed to late volume
+        assert_noop!(
+            RewardModule::save_trading(&alice, 1000, 150),
+            Error::<Test>::EraFinalized
+        );
+        ExactDistribution::set(false);
+        OperatorShare::set(Perquintill::zero());
+    });
+}
+
+#[test]
+fn test_funded_emission_should_draw_down_the_pool() {
+    new_test_ext().execute_with(|| {
+        RewardsPerEra::set(4 * DOLLARS);
+        FundedEmission::set(true);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let pool = RewardModule::pool_account();
+        Balances::make_free_balance_be(&alice, 10 * DOLLARS);
+        assert_noop!(
+            RewardModule::fund_pool(Origin::signed(alice.clone()), 11 * DOLLARS),
+            Error::<Test>::InsufficientBalance
+        );
+        assert_ok!(RewardModule::fund_pool(
+            Origin::signed(alice.clone()),
+            5 * DOLLARS
+        ));
+        System::assert_last_event(crate::mock::Event::RewardModule(crate::Event::PoolFunded(
+            alice.clone(),
+            5 * DOLLARS,
+        )));
+        assert_eq!(RewardModule::pool_balance(), 5 * DOLLARS);
+        assert_eq!(Balances::free_balance(&pool), 5 * DOLLARS);
+        assert_eq!(Balances::free_balance(&alice), 5 * DOLLARS);
+
+        frame_system::Pallet::<Test>::set_block_number(150);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), 9 * DOLLARS);
+        assert_eq!(RewardModule::pool_balance(), DOLLARS);
+        assert_eq!(Balances::free_balance(&pool), DOLLARS);
+
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 250));
+        frame_system::Pallet::<Test>::set_block_number(350);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::PoolDepleted(3 * DOLLARS),
+        ));
+        assert_eq!(Balances::free_balance(&alice), 10 * DOLLARS);
+        assert_eq!(RewardModule::pool_balance(), 0);
+        assert_eq!(Balances::free_balance(&pool), 0);
+        assert_eq!(RewardModule::total_distributed(), 5 * DOLLARS);
+        FundedEmission::set(false);
+        RewardsPerEra::set(1000000000000000000000000);
+    });
+}
+
+#[test]
+fn test_verify_era_should_detect_corruption() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        assert!(!RewardModule::verify_era(100));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert!(RewardModule::verify_era(100));
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert!(RewardModule::verify_era(100));
+
+        let confirmed = RewardModule::era_rewards(100, &alice);
+        EraRewards::<Test>::insert(100, &alice, confirmed + 10);
+        assert!(!RewardModule::verify_era(100));
+        EraRewards::<Test>::insert(100, &alice, confirmed - 10);
+        assert!(!RewardModule::verify_era(100));
+    });
+}
+
+#[test]
+fn test_strict_claims_should_reject_open_era_pending() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardClaimed(alice.clone(), 0),
+        ));
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+
+        StrictClaims::set(true);
+        assert_noop!(
+            RewardModule::take_reward(Origin::signed(alice.clone())),
+            Error::<Test>::EraNotClosed
+        );
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), RewardsPerEra::get());
+        StrictClaims::set(false);
+    });
+}
+
+#[test]
+fn test_export_pages_should_reconstruct_the_state() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(250);
+        let traders: Vec<AccountId> = vec![
+            AccountKeyring::Alice.into(),
+            AccountKeyring::Bob.into(),
+            AccountKeyring::Charlie.into(),
+            AccountKeyring::Dave.into(),
+            AccountKeyring::Eve.into(),
+        ];
+        for (i, who) in traders.iter().enumerate() {
+            assert_ok!(RewardModule::save_trading(who, 100 * (i as u64 + 1), 150));
+        }
+        assert_ok!(RewardModule::save_trading(&traders[0], 100, 250));
+
+        let (mut rewards, mut pages, mut cursor) = (BTreeMap::new(), 0, None);
+        loop {
+            let (page, next) = RewardModule::export_state(cursor);
+            assert!(page.len() <= MaxExportPage::get() as usize);
+            rewards.extend(page);
+            pages += 1;
+            cursor = next;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(rewards, Rewards::<Test>::iter().collect::<BTreeMap<_, _>>());
+        assert_eq!(rewards.len(), traders.len());
+
+        let (mut volumes, mut cursor) = (Vec::new(), None);
+        loop {
+            let (page, next) = RewardModule::export_volumes(cursor);
+            volumes.extend(page);
+            cursor = next;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(volumes.len(), 6);
+        let total: u64 = volumes
+            .iter()
+            .filter(|(era, _, _)| *era == 100)
+            .map(|(_, _, vol)| vol)
+            .sum();
+        assert_eq!(total, RewardModule::volumes(RewardModule::era_of(100)));
+
+        // an account written between the pages shifts none of the others
+        let (page, cursor) = RewardModule::export_state(None);
+        let mut exported: Vec<AccountId> = page.into_iter().map(|(who, _)| who).collect();
+        let ferdie: AccountId = AccountKeyring::Ferdie.into();
+        assert_ok!(RewardModule::save_trading(&ferdie, 100, 250));
+        let mut cursor = cursor;
+        while cursor.is_some() {
+            let (page, next) = RewardModule::export_state(cursor);
+            exported.extend(page.into_iter().map(|(who, _)| who));
+            cursor = next;
+        }
+        let unique: BTreeSet<AccountId> = exported.iter().cloned().collect();
+        assert_eq!(unique.len(), exported.len());
+        assert!(traders.iter().all(|who| unique.contains(who)));
+    });
+}
+
+#[test]
+fn test_era_rotation_accrual_should_match_the_pallet() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let pool = RewardsPerEra::get();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 150));
+        assert_eq!(<EraRotationAccrual as Accrual<Test>>::claimable(&alice), 0);
+
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_eq!(
+            <EraRotationAccrual as Accrual<Test>>::claimable(&alice),
+            pool / 4
+        );
+        assert_eq!(RewardModule::total_claimable(&bob), pool / 4 * 3);
+        assert_ok!(
+            <EraRotationAccrual as Accrual<Test>>::settle(&alice, RewardModule::era_of(250)),
+            pool / 4
+        );
+        assert_eq!(
+            RewardModule::rewards(&alice),
+            Reward {
+                confirmed: pool / 4,
+                pending_vol: 0,
+                last_modify: 100
+            }
+        );
+        assert_ok!(<EraRotationAccrual as Accrual<Test>>::record(
+            &alice,
+            500,
+            RewardModule::era_of(250)
+        ));
+        assert_eq!(
+            RewardModule::rewards(&alice),
+            Reward {
+                confirmed: pool / 4,
+                pending_vol: 500,
+                last_modify: 200
+            }
+        );
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(Balances::free_balance(&bob), pool / 4 * 3);
+    });
+}
+
+#[test]
+fn test_pair_volumes_should_be_reported_apart() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(250);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let (btc_usdt, eth_usdt) = (1u32, 2u32);
+        assert_ok!(RewardModule::save_pair_trading(
+            Some(btc_usdt),
+            &alice,
+            1000,
+            150
+        ));
+        assert_ok!(RewardModule::save_pair_trading(
+            Some(eth_usdt),
+            &bob,
+            3000,
+            160
+        ));
+        assert_ok!(RewardModule::save_pair_trading(
+            Some(btc_usdt),
+            &bob,
+            500,
+            170
+        ));
+        assert_ok!(RewardModule::save_pair_trading(None, &alice, 200, 180));
+        assert_ok!(RewardModule::save_pair_trading(
+            Some(eth_usdt),
+            &alice,
+            100,
+            250
+        ));
+        assert_eq!(RewardModule::pair_volume(btc_usdt, 199), 1500);
+        assert_eq!(RewardModule::pair_volume(eth_usdt, 100), 3000);
+        assert_eq!(RewardModule::pair_volume(eth_usdt, 200), 100);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 4700);
+
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(
+            Balances::free_balance(&bob),
+            Perquintill::from_rational(3500u128, 4700u128).mul_floor(RewardsPerEra::get())
+        );
+    });
+}
+
+#[test]
+fn test_capped_claim_should_leave_the_excess_confirmed() {
+    new_test_ext().execute_with(|| {
+        RewardsPerEra::set(10 * DOLLARS);
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_ok!(RewardModule::take_reward_capped(
+            Origin::signed(alice.clone()),
+            4 * DOLLARS
+        ));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardClaimed(alice.clone(), 4 * DOLLARS),
+        ));
+        assert_eq!(Balances::free_balance(&alice), 4 * DOLLARS);
+        assert_eq!(RewardModule::rewards(&alice).confirmed, 6 * DOLLARS);
+
+        assert_ok!(RewardModule::take_reward_capped(
+            Origin::signed(alice.clone()),
+            20 * DOLLARS
+        ));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardClaimed(alice.clone(), 6 * DOLLARS),
+        ));
+        assert_eq!(Balances::free_balance(&alice), 10 * DOLLARS);
+        assert_eq!(RewardModule::rewards(&alice), Default::default());
+        assert_eq!(RewardModule::total_claimed(), 10 * DOLLARS);
+        RewardsPerEra::set(1000000000000000000000000);
+    });
+}
+
+#[test]
+fn test_volume_checkpoint_should_detect_drift() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 160));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::confirm_volume_checkpoint(150, 4000));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::VolumeCheckpointConfirmed(100, 4000),
+        ));
+        assert_noop!(
+            RewardModule::confirm_volume_checkpoint(100, 4001),
+            Error::<Test>::CheckpointMismatch
+        );
+        assert_noop!(
+            RewardModule::confirm_volume_checkpoint(200, 4000),
+            Error::<Test>::CheckpointMismatch
+        );
+    });
+}
+
+#[test]
+fn test_active_blocks_should_weight_equal_volumes() {
+    new_test_ext().execute_with(|| {
+        ActiveBlockWeighting::set(true);
+        RewardsPerEra::set(3 * DOLLARS);
+        frame_system::Pallet::<Test>::set_block_number(199);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 500, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 500, 150));
+        assert_ok!(RewardModule::save_trading(&alice, 500, 199));
+        assert_ok!(RewardModule::save_trading(&bob, 500, 199));
+        assert_eq!(RewardModule::active_spans(100, &alice), Some((100, 199)));
+        assert_eq!(RewardModule::active_spans(100, &bob), Some((150, 199)));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(
+            RewardModule::weighted_totals(100),
+            Some(1000 * 100 + 1000 * 50)
+        );
+        assert_eq!(Balances::free_balance(&alice), 2 * DOLLARS);
+        assert_eq!(Balances::free_balance(&bob), DOLLARS);
+        // verified and simulated with the weighted shares, not the plain ones
+        assert!(RewardModule::verify_era(100));
+        let mut simulated = RewardModule::simulate_era(100, 0, 10);
+        simulated.sort_by_key(|(_, r)| *r);
+        assert_eq!(simulated, vec![(bob, DOLLARS), (alice, 2 * DOLLARS)]);
+        ActiveBlockWeighting::set(false);
+        RewardsPerEra::set(1000000000000000000000000);
+    });
+}
+
+#[test]
+fn test_transferred_volume_should_keep_its_active_span() {
+    new_test_ext().execute_with(|| {
+        ActiveBlockWeighting::set(true);
+        RewardsPerEra::set(2 * DOLLARS);
+        frame_system::Pallet::<Test>::set_block_number(199);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 199));
+        assert_ok!(RewardModule::save_trading(&bob, 500, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 500, 199));
+        assert_ok!(RewardModule::transfer_pending_volume(
+            Origin::signed(alice.clone()),
+            charlie.clone(),
+            2000
+        ));
+        assert_eq!(RewardModule::active_spans(100, &alice), None);
+        assert_eq!(RewardModule::active_spans(100, &charlie), Some((150, 199)));
+
+        // charlie is weighted by the 50 blocks alice was active, not the whole era
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_ok!(RewardModule::take_reward(Origin::signed(charlie.clone())));
+        assert_eq!(
+            RewardModule::weighted_totals(100),
+            Some(2000 * 50 + 1000 * 100)
+        );
+        assert_eq!(Balances::free_balance(&charlie), DOLLARS);
+        assert_eq!(Balances::free_balance(&bob), DOLLARS);
+        ActiveBlockWeighting::set(false);
+        RewardsPerEra::set(1000000000000000000000000);
+    });
+}
+
+#[test]
+fn test_volume_credited_without_trading_should_record_its_span() {
+    new_test_ext().execute_with(|| {
+        ActiveBlockWeighting::set(true);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        assert_ok!(RewardModule::import_volume_snapshot(
+            Origin::root(),
+            vec![(alice.clone(), 1000, 100)].try_into().unwrap()
+        ));
+        assert_ok!(RewardModule::set_virtual_volume(
+            Origin::root(),
+            charlie.clone(),
+            1000
+        ));
+        frame_system::Pallet::<Test>::set_block_number(100);
+        RewardModule::on_initialize(100);
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 150));
+        assert_eq!(RewardModule::active_spans(100, &alice), Some((100, 199)));
+        assert_eq!(RewardModule::active_spans(100, &charlie), Some((100, 199)));
+        assert_eq!(RewardModule::active_spans(100, &bob), Some((150, 150)));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(
+            RewardModule::weighted_totals(100),
+            Some(1000 * 100 + 1000 * 100 + 1000)
+        );
+        ActiveBlockWeighting::set(false);
+    });
+}
+
+#[test]
+fn test_min_denominator_should_cap_a_lone_trader() {
+    new_test_ext().execute_with(|| {
+        MinDenominator::set(10_000);
+        RewardsPerEra::set(10 * DOLLARS);
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_eq!(RewardModule::projected_reward(&alice), DOLLARS);
+        assert_eq!(
+            RewardModule::simulate_era(100, 0, 10),
+            vec![(alice.clone(), DOLLARS)]
+        );
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), DOLLARS);
+        assert_eq!(RewardModule::remaining_pool(100), 9 * DOLLARS);
+
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&alice, 20_000, 250));
+        assert_ok!(RewardModule::save_trading(&bob, 20_000, 250));
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(Balances::free_balance(&bob), 5 * DOLLARS);
+        MinDenominator::set(0);
+        RewardsPerEra::set(1000000000000000000000000);
+    });
+}
+
+#[test]
+fn test_min_denominator_should_apply_in_every_mode() {
+    new_test_ext().execute_with(|| {
+        MinDenominator::set(10_000);
+        RewardsPerEra::set(10 * DOLLARS);
+        ExactDistribution::set(true);
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), DOLLARS);
+        ExactDistribution::set(false);
+
+        // active for the whole era, as the floor is weighted
+        ActiveBlockWeighting::set(true);
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_ok!(RewardModule::save_trading(&bob, 500, 200));
+        frame_system::Pallet::<Test>::set_block_number(299);
+        assert_ok!(RewardModule::save_trading(&bob, 500, 299));
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(Balances::free_balance(&bob), DOLLARS);
+        ActiveBlockWeighting::set(false);
+        MinDenominator::set(0);
+        RewardsPerEra::set(1000000000000000000000000);
+    });
+}
+
+#[test]
+fn test_pool_accessors_should_show_the_runway() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let pool = RewardModule::pool_account();
+        assert_eq!(
+            pool,
+            <Test as Config>::PalletId::get().into_account::<AccountId>()
+        );
+        assert_ne!(pool, alice);
+        assert_eq!(RewardModule::pool_balance(), 0);
+        Balances::make_free_balance_be(&alice, 10 * DOLLARS);
+        assert_ok!(RewardModule::fund_pool(
+            Origin::signed(alice.clone()),
+            3 * DOLLARS
+        ));
+        assert_ok!(RewardModule::fund_pool(
+            Origin::signed(alice.clone()),
+            2 * DOLLARS
+        ));
+        assert_eq!(RewardModule::pool_balance(), 5 * DOLLARS);
+        assert_eq!(Balances::free_balance(&pool), RewardModule::pool_balance());
+    });
+}
+
+#[test]
+fn test_claim_burn_should_credit_the_rest() {
+    new_test_ext().execute_with(|| {
+        ClaimBurn::set(Perquintill::from_percent(20));
+        RewardsPerEra::set(10 * DOLLARS);
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardBurned(alice.clone(), 2 * DOLLARS),
+        ));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::RewardClaimed(alice.clone(), 8 * DOLLARS),
+        ));
+        assert_eq!(Balances::free_balance(&alice), 8 * DOLLARS);
+        assert_eq!(RewardModule::rewards(&alice).confirmed, 0);
+        assert_eq!(RewardModule::total_claimed(), 10 * DOLLARS);
+        ClaimBurn::set(Perquintill::zero());
+        RewardsPerEra::set(1000000000000000000000000);
+    });
+}
+
+#[test]
+fn test_update_params_should_apply_all_fields() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let params = ParamUpdate {
+            era_duration: Some(50),
+            rewards_per_era: Some(7 * DOLLARS),
+            operator_share: Some(Perquintill::from_percent(10)),
+        };
+        assert_noop!(
+            RewardModule::update_params(Origin::signed(alice), params.clone()),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            RewardModule::update_params(
+                Origin::root(),
+                ParamUpdate {
+                    era_duration: Some(0),
+                    ..params.clone()
+                }
+            ),
+            Error::<Test>::InvalidEraDuration
+        );
+
+        assert_ok!(RewardModule::update_params(Origin::root(), params.clone()));
+        System::assert_last_event(crate::mock::Event::RewardModule(
+            crate::Event::ParamsUpdated(params),
+        ));
+        assert_eq!(RewardModule::era_duration(), 100);
+        assert_eq!(RewardModule::era_alignments(), vec![(100, 50)]);
+        assert_eq!(RewardModule::rewards_per_era(), 7 * DOLLARS);
+        assert_eq!(RewardModule::era_reward_at(100), 7 * DOLLARS);
+        assert_eq!(
+            RewardModule::operator_share(),
+            Perquintill::from_percent(10)
+        );
+        assert_eq!(
+            RewardModule::traders_pool(100),
+            7 * DOLLARS - 7 * DOLLARS / 10
+        );
+
+        assert_ok!(RewardModule::update_params(
+            Origin::root(),
+            ParamUpdate {
+                rewards_per_era: Some(DOLLARS),
+                ..Default::default()
+            }
+        ));
+        assert_eq!(RewardModule::era_alignments(), vec![(100, 50)]);
+        assert_eq!(RewardModule::rewards_per_era(), DOLLARS);
+        assert_eq!(
+            RewardModule::operator_share(),
+            Perquintill::from_percent(10)
+        );
+    });
+}
+
+#[test]
+fn test_params_should_be_frozen_once_era_closes() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let operator: AccountId = AccountKeyring::Eve.into();
+        let reward = RewardsPerEra::get();
+        OperatorShare::set(Perquintill::from_percent(20));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        RewardModule::on_initialize(200);
+        assert_eq!(
+            RewardModule::era_params(100),
+            Some((reward, Perquintill::from_percent(20)))
+        );
+        assert_eq!(RewardModule::rewards(&operator).confirmed, reward / 5);
+
+        // the closed era keeps the pool the operator was paid its cut of
+        assert_ok!(RewardModule::update_params(
+            Origin::root(),
+            ParamUpdate {
+                rewards_per_era: Some(2 * reward),
+                operator_share: Some(Perquintill::zero()),
+                ..Default::default()
+            }
+        ));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(RewardModule::rewards(&alice).confirmed, reward * 4 / 5);
+        assert_eq!(RewardModule::total_distributed(), reward);
+
+        frame_system::Pallet::<Test>::set_block_number(300);
+        RewardModule::on_initialize(300);
+        assert_eq!(RewardModule::rewards(&operator).confirmed, reward / 5);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 300));
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            reward * 4 / 5 + 2 * reward
+        );
+        OperatorShare::set(Perquintill::zero());
+    });
+}
+
+#[test]
+fn test_account_status_should_tell_each_state() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(150);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        assert_eq!(
+            RewardModule::account_status(&alice),
+            RewardStatus::NeverTraded
+        );
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        assert_eq!(
+            RewardModule::account_status(&alice),
+            RewardStatus::PendingOnly
+        );
+
+        frame_system::Pallet::<Test>::set_block_number(250);
+        assert_ok!(RewardModule::save_trading(&bob, 1000, 250));
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 250));
+        assert_eq!(
+            RewardModule::account_status(&alice),
+            RewardStatus::ConfirmedUnclaimed
+        );
+        assert_eq!(
+            RewardModule::account_status(&bob),
+            RewardStatus::PendingOnly
+        );
+
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(RewardModule::rewards(&alice), Default::default());
+        assert_eq!(
+            RewardModule::account_status(&alice),
+            RewardStatus::SettledZero
+        );
+    });
+}
+
+#[test]
+fn test_pruning_should_be_spread_over_blocks() {
+    new_test_ext().execute_with(|| {
+        MaxPrunePerBlock::set(3);
+        let accounts: Vec<AccountId> = vec![
+            AccountKeyring::Alice.into(),
+            AccountKeyring::Bob.into(),
+            AccountKeyring::Charlie.into(),
+        ];
+        for era in [0u32, 100, 200, 500] {
+            for who in accounts.iter() {
+                EraRewards::<Test>::insert(era, who, 1);
+            }
+        }
+        assert_eq!(EraRewards::<Test>::iter().count(), 12);
+
+        let mut blocks = 0;
+        let mut left = 12;
+        for now in 700..720 {
+            frame_system::Pallet::<Test>::set_block_number(now);
+            RewardModule::on_initialize(now);
+            let count = EraRewards::<Test>::iter().count();
+            assert!(left - count <= MaxPrunePerBlock::get() as usize);
+            left = count;
+            blocks += 1;
+            if RewardModule::prune_cursor() > 300 {
+                break;
+            }
+        }
+        assert!(blocks > 3);
+        assert_eq!(RewardModule::prune_cursor(), 400);
+        assert_eq!(EraRewards::<Test>::iter().count(), 3);
+        assert_eq!(EraRewards::<Test>::iter_prefix(500).count(), 3);
+        MaxPrunePerBlock::set(16);
+    });
+}
+
+#[test]
+fn test_marginal_reward_per_volume() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let reward = RewardsPerEra::get();
+        assert_eq!(RewardModule::marginal_reward_per_volume(100, 150), reward);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 3000, 120));
+        assert_eq!(
+            RewardModule::marginal_reward_per_volume(150, 150),
+            reward / 4000
+        );
+        assert_eq!(RewardModule::marginal_reward_per_volume(100, 200), 0);
+    });
+}
+
+#[test]
+fn test_boosted_eras_should_list_donations_and_schedules() {
+    new_test_ext().execute_with(|| {
+        let ferdie: AccountId = AccountKeyring::Ferdie.into();
+        Balances::make_free_balance_be(&ferdie, 10 * DOLLARS);
+        frame_system::Pallet::<Test>::set_block_number(150);
+        assert!(RewardModule::boosted_eras(100, 400).is_empty());
+        assert_ok!(RewardModule::donate_to_era(
+            Origin::signed(ferdie.clone()),
+            200,
+            DOLLARS
+        ));
+        assert_ok!(RewardModule::schedule_emission(
+            Origin::root(),
+            350,
+            399,
+            10
+        ));
+        assert_eq!(
+            RewardModule::boosted_eras(100, 400),
+            vec![(200, DOLLARS), (300, 10)]
+        );
+        assert_eq!(RewardModule::boosted_eras(250, 300), vec![(300, 10)]);
+        // cut at `MaxHistoryRange` eras
+        assert_eq!(RewardModule::boosted_eras(0, 400), vec![(200, DOLLARS)]);
+    });
+}
+
+#[test]
+fn test_accrual_should_be_suspended_above_max_issuance() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let ferdie: AccountId = AccountKeyring::Ferdie.into();
+        Balances::make_free_balance_be(&ferdie, 10 * DOLLARS);
+        MaxIssuance::set(5 * DOLLARS);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::AccrualSuspendedForSupply(alice.clone(), 10 * DOLLARS),
+        ));
+        // the volume of the suspended era is deferred, not dropped
+        assert_eq!(RewardModule::rewards(&alice).confirmed, 0);
+        assert_eq!(RewardModule::staged_volumes(&alice, 100), 1000);
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+        assert_eq!(RewardModule::rewards(&alice).last_modify, 200);
+
+        Balances::make_free_balance_be(&ferdie, 2 * DOLLARS);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            RewardsPerEra::get()
+        );
+        assert_eq!(RewardModule::staged_volumes(&alice, 100), 0);
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 2000);
+        assert_eq!(RewardModule::rewards(&alice).last_modify, 200);
+
+        // the confirmed rewards count in the supply though minted only on claim
+        MaxIssuance::set(RewardsPerEra::get());
+        frame_system::Pallet::<Test>::set_block_number(300);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 300));
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::AccrualSuspendedForSupply(
+                alice.clone(),
+                RewardsPerEra::get() + 2 * DOLLARS,
+            ),
+        ));
+        assert_eq!(RewardModule::staged_volumes(&alice, 200), 2000);
+        MaxIssuance::set(u128::MAX);
+    });
+}
+
+#[test]
+fn test_report_volume_should_be_permissioned() {
+    use crate::weights::WeightInfo;
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let dave: AccountId = AccountKeyring::Dave.into();
+        assert_noop!(
+            RewardModule::report_volume(Origin::signed(alice.clone()), alice.clone(), 1000, 100),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            RewardModule::report_volume(Origin::root(), alice.clone(), 1000, 100),
+            DispatchError::BadOrigin
+        );
+        let post =
+            RewardModule::report_volume(Origin::signed(dave.clone()), alice.clone(), 1000, 100)
+                .unwrap();
+        assert_eq!(
+            post.actual_weight,
+            Some(<() as WeightInfo>::save_trading_same_era())
+        );
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 1000);
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(100)), 1000);
+        // settling the pending era of alice is charged in full
+        frame_system::Pallet::<Test>::set_block_number(200);
+        let post =
+            RewardModule::report_volume(Origin::signed(dave), alice.clone(), 1000, 200).unwrap();
+        assert_eq!(
+            post.actual_weight,
+            Some(<() as WeightInfo>::save_trading_rotate())
+        );
+    });
+}
+
+#[test]
+fn test_run_to_block_should_close_eras() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        run_to_block(150);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 150));
+        run_to_block(199);
+        assert!(!RewardModule::is_era_closed(100, System::block_number()));
+        run_to_block(200);
+        System::assert_has_event(crate::mock::Event::RewardModule(crate::Event::EraClosed(
+            100,
+            1000,
+            1,
+            RewardsPerEra::get(),
+        )));
+        assert_eq!(RewardModule::era_share(&alice, 100), Perquintill::one());
+    });
+}
+
+#[test]
+fn test_overflowing_volume_should_roll_over_to_next_era() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        run_to_block(110);
+        assert_ok!(RewardModule::save_trading(&alice, u64::MAX - 10, 110));
+        run_to_block(120);
+        assert_eq!(
+            RewardModule::save_trading(&bob, 100, 120),
+            Err(Error::<Test>::Overflow.into())
+        );
+
+        VolumeRollover::set(true);
+        run_to_block(130);
+        assert_ok!(RewardModule::save_trading(&bob, 100, 130));
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::EraRolledOver(100, 200),
+        ));
+        assert!(RewardModule::rolled_over_eras(100));
+        assert_eq!(
+            RewardModule::volumes(RewardModule::era_of(100)),
+            u64::MAX - 10
+        );
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(200)), 100);
+        assert_eq!(RewardModule::rewards(&bob).last_modify, 200);
+
+        // the rolled over era takes no more volume even if it would fit
+        run_to_block(140);
+        assert_ok!(RewardModule::save_trading(&charlie, 5, 140));
+        assert_eq!(
+            RewardModule::volumes(RewardModule::era_of(100)),
+            u64::MAX - 10
+        );
+        assert_eq!(RewardModule::volumes(RewardModule::era_of(200)), 105);
+
+        // the pending volume of the rolled over era is settled from its final total
+        run_to_block(150);
+        assert_ok!(RewardModule::save_trading(&alice, 10, 150));
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            RewardsPerEra::get()
+        );
+        assert_eq!(RewardModule::rewards(&alice).last_modify, 200);
+        VolumeRollover::set(false);
+    });
+}
+
+#[test]
+fn test_era_share_precise_should_match_confirmation() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        RecordShares::set(true);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 100));
+        assert_ok!(RewardModule::save_trading(&bob, 2000, 100));
+        frame_system::Pallet::<Test>::set_block_number(200);
+        assert_ok!(RewardModule::save_trading(&alice, 1000, 200));
+        let share = RewardModule::era_share_precise(&alice, 150).unwrap();
+        assert_eq!(share, RewardModule::era_share(&alice, 100));
+        assert_eq!(share, Perquintill::from_rational(1000u128, 3000u128));
+        assert_eq!(
+            RewardModule::rewards(&alice).confirmed,
+            share.mul_floor(RewardsPerEra::get())
+        );
+        assert_eq!(RewardModule::era_share_precise(&bob, 100), None);
+
+        // dropped with the era out of the history window
+        frame_system::Pallet::<Test>::set_block_number(500);
+        RewardModule::on_initialize(500);
+        assert_eq!(RewardModule::era_share_precise(&alice, 100), None);
+        RecordShares::set(false);
+    });
+}
+
+#[test]
+fn test_claim_within_rolled_over_window_should_not_settle_next_era() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let bob: AccountId = AccountKeyring::Bob.into();
+        let charlie: AccountId = AccountKeyring::Charlie.into();
+        VolumeRollover::set(true);
+        run_to_block(110);
+        assert_ok!(RewardModule::save_trading(&alice, u64::MAX - 10, 110));
+        run_to_block(120);
+        assert_ok!(RewardModule::save_trading(&bob, 100, 120));
+        assert_eq!(RewardModule::rewards(&bob).last_modify, 200);
+
+        run_to_block(130);
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_eq!(RewardModule::rewards(&bob).confirmed, 0);
+        assert_eq!(RewardModule::rewards(&bob).pending_vol, 100);
+        assert_eq!(RewardModule::rewards(&bob).last_modify, 200);
+        assert_eq!(RewardModule::era_distributed(200), 0);
+
+        // the rolled over era itself is final and settled by the claim
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), RewardsPerEra::get());
+        assert_eq!(RewardModule::rewards(&alice).pending_vol, 0);
+
+        // the era taking the rolled over volume is settled once closed
+        assert_ok!(RewardModule::save_trading(&charlie, 300, 140));
+        run_to_block(300);
+        assert_ok!(RewardModule::take_reward(Origin::signed(bob.clone())));
+        assert_ok!(RewardModule::take_reward(Origin::signed(charlie.clone())));
+        assert_eq!(Balances::free_balance(&bob), RewardsPerEra::get() / 4);
+        assert_eq!(
+            Balances::free_balance(&charlie),
+            RewardsPerEra::get() * 3 / 4
+        );
+        VolumeRollover::set(false);
+    });
+}
+
+#[test]
+fn test_reserved_claims_should_vest_too() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(300);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        InstantShare::set(Perquintill::from_percent(40));
+        Rewards::<Test>::insert(
+            &alice,
+            Reward {
+                confirmed: 10 * DOLLARS,
+                pending_vol: 0,
+                last_modify: 100,
+            },
+        );
+        assert_ok!(RewardModule::claim_and_lock(
+            Origin::signed(alice.clone()),
+            0
+        ));
+        assert_eq!(Balances::reserved_balance(&alice), 10 * DOLLARS);
+        assert_eq!(RewardModule::reward_locks(&alice), Some((4 * DOLLARS, 300)));
+        assert_ok!(RewardModule::unlock_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), 4 * DOLLARS);
+        assert_eq!(Balances::reserved_balance(&alice), 6 * DOLLARS);
+
+        // a later claim merges into the running schedule instead of restarting it
+        frame_system::Pallet::<Test>::set_block_number(350);
+        Rewards::<Test>::insert(
+            &alice,
+            Reward {
+                confirmed: 10 * DOLLARS,
+                pending_vol: 0,
+                last_modify: 200,
+            },
+        );
+        assert_ok!(RewardModule::take_reward(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), 11 * DOLLARS);
+        assert_eq!(
+            RewardModule::vesting(&alice),
+            Some(VestingInfo {
+                total: 9 * DOLLARS,
+                released: 0,
+                start: 350,
+                end: 350 + (3 * 50 + 6 * 100) / 9,
+            })
+        );
+        frame_system::Pallet::<Test>::set_block_number(500);
+        assert_ok!(RewardModule::vest(Origin::signed(alice.clone())));
+        assert_eq!(Balances::free_balance(&alice), 20 * DOLLARS);
+        assert_eq!(RewardModule::vesting(&alice), None);
+        InstantShare::set(Perquintill::one());
+    });
+}
+
+#[test]
+fn test_claim_tip_should_be_capped_at_instant_part() {
+    new_test_ext().execute_with(|| {
+        frame_system::Pallet::<Test>::set_block_number(300);
+        let alice: AccountId = AccountKeyring::Alice.into();
+        let keeper: AccountId = AccountKeyring::Ferdie.into();
+        Balances::make_free_balance_be(&alice, DOLLARS);
+        Balances::make_free_balance_be(&keeper, DOLLARS);
+        InstantShare::set(Perquintill::from_percent(5));
+        Rewards::<Test>::insert(
+            &alice,
+            Reward {
+                confirmed: 10 * DOLLARS,
+                pending_vol: 0,
+                last_modify: 100,
+            },
+        );
+        let tip = Perquintill::from_percent(10);
+        assert_ok!(RewardModule::opt_in_cranking(
+            Origin::signed(alice.clone()),
+            Some(tip)
+        ));
+        assert_ok!(RewardModule::claim_with_tip(
+            Origin::signed(keeper.clone()),
+            alice.clone(),
+            tip
+        ));
+        // the balance of alice besides the claim is untouched
+        assert_eq!(Balances::free_balance(&alice), DOLLARS);
+        assert_eq!(Balances::reserved_balance(&alice), 10 * DOLLARS * 95 / 100);
+        assert_eq!(Balances::free_balance(&keeper), DOLLARS + DOLLARS / 2);
+        System::assert_has_event(crate::mock::Event::RewardModule(
+            crate::Event::CrankTipPaid(keeper, alice, DOLLARS / 2),
+        ));
+        InstantShare::set(Perquintill::one());
     });
 }
@@ -0,0 +1,53 @@
+//! Weights for pallet_fuso_reward
+//!
+//! PLACEHOLDER: these are estimates, not the output of a benchmark run. Regenerate them from
+//! `benchmarking.rs` with frame-benchmarking-cli before relying on them:
+//!
+//! ```text
+//! <node> benchmark --chain=dev --execution=wasm --wasm-execution=compiled \
+//!     --pallet=pallet_fuso_reward --extrinsic='*' --steps=50 --repeat=20 \
+//!     --output=./pallet-fuso-reward/src/weights.rs
+//! ```
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{RuntimeDbWeight, Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+pub trait WeightInfo {
+	fn save_trading_same_era() -> Weight;
+	fn save_trading_rotate() -> Weight;
+}
+
+fn save_trading_same_era(db: RuntimeDbWeight) -> Weight {
+	(38_420_000 as Weight)
+		.saturating_add(db.reads(6 as Weight))
+		.saturating_add(db.writes(4 as Weight))
+}
+
+fn save_trading_rotate(db: RuntimeDbWeight) -> Weight {
+	(93_150_000 as Weight)
+		.saturating_add(db.reads(13 as Weight))
+		.saturating_add(db.writes(9 as Weight))
+}
+
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn save_trading_same_era() -> Weight {
+		save_trading_same_era(T::DbWeight::get())
+	}
+	fn save_trading_rotate() -> Weight {
+		save_trading_rotate(T::DbWeight::get())
+	}
+}
+
+impl WeightInfo for () {
+	fn save_trading_same_era() -> Weight {
+		save_trading_same_era(RocksDbWeight::get())
+	}
+	fn save_trading_rotate() -> Weight {
+		save_trading_rotate(RocksDbWeight::get())
+	}
+}
@@ -468,15 +468,18 @@ pub mod pallet {
                 Error::<T>::DominatorInactive
             );
             let mut known_root = dominator.merkle_root;
+            // only the recording of the trades is charged, by their rotations
+            let mut weight: Weight = 0;
             for proof in proofs.into_iter() {
                 known_root = Self::verify_and_update(
                     &dominator_id,
                     known_root,
                     dominator.start_from.clone(),
                     proof,
+                    &mut weight,
                 )?;
             }
-            Ok(Some(0).into())
+            Ok(Some(weight).into())
         }
 
         #[transactional]
@@ -664,6 +667,7 @@ pub mod pallet {
             known_root: [u8; 32],
             claim_at: T::BlockNumber,
             proof: Proof<T::AccountId>,
+            weight: &mut Weight,
         ) -> Result<[u8; 32], DispatchError> {
             let mp = smt::CompiledMerkleProof(proof.merkle_proof.clone());
             let (old, new): (Vec<_>, Vec<_>) = proof
@@ -717,11 +721,21 @@ pub mod pallet {
                         dominator_id,
                         &proof.leaves,
                     )?;
+                    let native = T::Asset::native_token_id();
+                    let native_pair = native == base.into() || native == quote.into();
                     if cr.users_mutation.len() > 1 {
                         for d in cr.users_mutation.iter() {
                             Self::clear(&d.who, dominator_id, base.into(), d.base_value)?;
                             Self::clear(&d.who, dominator_id, quote.into(), d.quote_value)?;
-                            T::Rewarding::save_trading(&d.who, d.volume, current_block)?;
+                            *weight = weight.saturating_add(T::Rewarding::save_trading_weight(
+                                &d.who,
+                                current_block,
+                            ));
+                            if native_pair {
+                                T::Rewarding::save_native_trading(&d.who, d.volume, current_block)?;
+                            } else {
+                                T::Rewarding::save_trading(&d.who, d.volume, current_block)?;
+                            }
                         }
                     }
                     Self::put_profit(dominator_id, current_season, quote.into(), cr.quote_fee)?;
@@ -763,11 +777,21 @@ pub mod pallet {
                         dominator_id,
                         &proof.leaves,
                     )?;
+                    let native = T::Asset::native_token_id();
+                    let native_pair = native == base.into() || native == quote.into();
                     if cr.users_mutation.len() > 1 {
                         for d in cr.users_mutation.iter() {
                             Self::clear(&d.who, dominator_id, base.into(), d.base_value)?;
                             Self::clear(&d.who, dominator_id, quote.into(), d.quote_value)?;
-                            T::Rewarding::save_trading(&d.who, d.volume, current_block)?;
+                            *weight = weight.saturating_add(T::Rewarding::save_trading_weight(
+                                &d.who,
+                                current_block,
+                            ));
+                            if native_pair {
+                                T::Rewarding::save_native_trading(&d.who, d.volume, current_block)?;
+                            } else {
+                                T::Rewarding::save_trading(&d.who, d.volume, current_block)?;
+                            }
                         }
                     }
                     Self::put_profit(dominator_id, current_season, quote.into(), cr.quote_fee)?;
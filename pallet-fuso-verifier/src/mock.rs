@@ -113,6 +113,14 @@ impl fuso_support::traits::Rewarding<AccountId, Balance, BlockNumber> for Phanto
         0
     }
 
+    fn is_era_closed(_era: BlockNumber, _at: BlockNumber) -> bool {
+        true
+    }
+
+    fn era_share(_who: &AccountId, _era: BlockNumber) -> sp_runtime::Perquintill {
+        sp_runtime::Perquintill::zero()
+    }
+
     fn save_trading(
         _trader: &AccountId,
         _amount: Balance,
@@ -120,6 +128,58 @@ impl fuso_support::traits::Rewarding<AccountId, Balance, BlockNumber> for Phanto
     ) -> frame_support::pallet_prelude::DispatchResult {
         Ok(())
     }
+
+    fn save_native_trading(
+        _trader: &AccountId,
+        _amount: Balance,
+        _at: BlockNumber,
+    ) -> frame_support::pallet_prelude::DispatchResult {
+        Ok(())
+    }
+
+    fn save_trading_weight(
+        _trader: &AccountId,
+        _at: BlockNumber,
+    ) -> frame_support::weights::Weight {
+        0
+    }
+
+    fn save_trading_pair(
+        _maker: &AccountId,
+        _taker: &AccountId,
+        _amount: Balance,
+        _at: BlockNumber,
+    ) -> frame_support::pallet_prelude::DispatchResult {
+        Ok(())
+    }
+
+    fn is_eligible(_who: &AccountId) -> bool {
+        true
+    }
+
+    fn confirm_volume_checkpoint(
+        _era: BlockNumber,
+        _expected_total: Balance,
+    ) -> frame_support::pallet_prelude::DispatchResult {
+        Ok(())
+    }
+
+    fn revoke_trading(
+        _trader: &AccountId,
+        _amount: Balance,
+        _at: BlockNumber,
+    ) -> frame_support::pallet_prelude::DispatchResult {
+        Ok(())
+    }
+
+    fn save_holding(
+        _trader: &AccountId,
+        _position: Balance,
+        _duration: BlockNumber,
+        _at: BlockNumber,
+    ) -> frame_support::pallet_prelude::DispatchResult {
+        Ok(())
+    }
 }
 
 impl pallet_fuso_verifier::Config for Test {